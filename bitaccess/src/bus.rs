@@ -0,0 +1,407 @@
+//! Registers of external chips, accessed over I2C or SPI with `embedded-hal` traits.
+
+use embedded_hal::{i2c, spi};
+
+pub use self::cache::Cached;
+
+mod cache;
+
+/// Registers accessed with [`embedded_hal::i2c::I2c`].
+pub struct I2c;
+
+/// Registers accessed with [`embedded_hal::spi::SpiDevice`].
+pub struct Spi;
+
+/// Bus used by `bus = ...` registers. `K` is either [`I2c`] or [`Spi`], so a single type
+/// may provide both protocols.
+///
+/// It's implemented for every `embedded-hal` I2C bus and SPI device, using
+/// [`Addressing::DEFAULT`], and for [`Device`], which wraps SPI devices with any other [`Addressing`]:
+/// * I2C registers are read with `write_read` of register address followed by the register
+///   contents, and written with a single write of register address followed by the contents
+/// * SPI registers are read with register address with `spi_read` bits set, followed by a read
+///   of the contents, and written with register address with `spi_write` bits set followed by
+///   the contents in a single transaction. `device` is ignored.
+pub trait RegisterBus<K> {
+    type Error: core::fmt::Debug;
+
+    fn read_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error>;
+    fn write_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error>;
+
+    /// Reads a register which changes on its own or when it's read, like status or
+    /// read-to-clear registers. Caches pass such reads directly to the bus.
+    fn read_volatile_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.read_register(device, register, buffer)
+    }
+
+    /// Writes a register with side effects, like write-one-to-clear registers.
+    /// Caches pass such writes directly to the bus.
+    fn write_volatile_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.write_register(device, register, data)
+    }
+}
+
+/// Address of a register within a chip, 8 or 16 bit long as declared with `register_width`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterAddress {
+    Narrow(u8),
+    /// Sent most significant byte first.
+    Wide(u16),
+}
+
+impl RegisterAddress {
+    /// Address regardless of its width.
+    pub fn get(self) -> u16 {
+        match self {
+            Self::Narrow(register) => register as u16,
+            Self::Wide(register) => register,
+        }
+    }
+
+    /// Address bytes with `flags` set in the first one, and how many are used.
+    fn encode(self, flags: u8) -> ([u8; 2], usize) {
+        match self {
+            Self::Narrow(register) => ([register | flags, 0], 1),
+            Self::Wide(register) => {
+                let [high, low] = register.to_be_bytes();
+                ([high | flags, low], 2)
+            }
+        }
+    }
+}
+
+/// How an SPI chip expects register addresses to be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Addressing {
+    /// Bits set in the first address byte of SPI reads.
+    pub spi_read: u8,
+    /// Bits set in the first address byte of SPI writes.
+    pub spi_write: u8,
+}
+
+impl Addressing {
+    /// SPI reads set the most significant bit of the address, writes send it unchanged.
+    pub const DEFAULT: Self = Self {
+        spi_read: 0x80,
+        spi_write: 0,
+    };
+}
+
+impl Default for Addressing {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// SPI device of a chip which doesn't use [`Addressing::DEFAULT`], eg. one setting the most
+/// significant bit of the address on writes:
+/// ```ignore,rust
+/// let sensor = Device::new(spi, Addressing { spi_read: 0, spi_write: 0x80 });
+/// ```
+pub struct Device<T> {
+    bus: T,
+    addressing: Addressing,
+}
+
+impl<T> Device<T> {
+    pub fn new(bus: T, addressing: Addressing) -> Self {
+        Self { bus, addressing }
+    }
+
+    /// Gives back the wrapped bus.
+    pub fn release(self) -> T {
+        self.bus
+    }
+}
+
+/// Longest register supported by the blanket implementations, in bytes.
+const MAX_REGISTER_SIZE: usize = 16;
+
+/// Register address followed by `data`, so I2C write happens in a single transfer.
+/// Returns the buffer and how much of it is used.
+fn prefixed(
+    (address, address_len): ([u8; 2], usize),
+    data: &[u8],
+) -> ([u8; MAX_REGISTER_SIZE + 2], usize) {
+    assert!(data.len() <= MAX_REGISTER_SIZE, "register too long");
+    let len = address_len + data.len();
+    let mut buffer = [0u8; MAX_REGISTER_SIZE + 2];
+    buffer[..address_len].copy_from_slice(&address[..address_len]);
+    buffer[address_len..len].copy_from_slice(data);
+    (buffer, len)
+}
+
+fn i2c_read<T: i2c::I2c>(
+    bus: &mut T,
+    device: u8,
+    register: RegisterAddress,
+    buffer: &mut [u8],
+) -> Result<(), T::Error> {
+    let (address, len) = register.encode(0);
+    bus.write_read(device, &address[..len], buffer)
+}
+
+fn i2c_write<T: i2c::I2c>(
+    bus: &mut T,
+    device: u8,
+    register: RegisterAddress,
+    data: &[u8],
+) -> Result<(), T::Error> {
+    let (buffer, len) = prefixed(register.encode(0), data);
+    bus.write(device, &buffer[..len])
+}
+
+fn spi_read<T: spi::SpiDevice>(
+    bus: &mut T,
+    addressing: Addressing,
+    register: RegisterAddress,
+    buffer: &mut [u8],
+) -> Result<(), T::Error> {
+    let (address, len) = register.encode(addressing.spi_read);
+    bus.transaction(&mut [
+        spi::Operation::Write(&address[..len]),
+        spi::Operation::Read(buffer),
+    ])
+}
+
+fn spi_write<T: spi::SpiDevice>(
+    bus: &mut T,
+    addressing: Addressing,
+    register: RegisterAddress,
+    data: &[u8],
+) -> Result<(), T::Error> {
+    let (address, len) = register.encode(addressing.spi_write);
+    bus.transaction(&mut [
+        spi::Operation::Write(&address[..len]),
+        spi::Operation::Write(data),
+    ])
+}
+
+impl<T: i2c::I2c> RegisterBus<I2c> for T {
+    type Error = T::Error;
+
+    fn read_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        i2c_read(self, device, register, buffer)
+    }
+
+    fn write_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        i2c_write(self, device, register, data)
+    }
+}
+
+impl<T: spi::SpiDevice> RegisterBus<Spi> for T {
+    type Error = T::Error;
+
+    fn read_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        spi_read(self, Addressing::DEFAULT, register, buffer)
+    }
+
+    fn write_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        spi_write(self, Addressing::DEFAULT, register, data)
+    }
+}
+
+impl<T: spi::SpiDevice> RegisterBus<Spi> for Device<T> {
+    type Error = T::Error;
+
+    fn read_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        spi_read(&mut self.bus, self.addressing, register, buffer)
+    }
+
+    fn write_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        spi_write(&mut self.bus, self.addressing, register, data)
+    }
+}
+
+/// Asynchronous counterpart of [`RegisterBus`], used by `bus = async_i2c` and `bus = async_spi`
+/// registers. Implemented for every `embedded-hal-async` I2C bus and SPI device, and for
+/// [`Device`] wrapping the latter, with the same transfers as [`RegisterBus`].
+#[cfg(feature = "embedded-hal-async")]
+#[allow(async_fn_in_trait)]
+pub trait AsyncRegisterBus<K> {
+    type Error: core::fmt::Debug;
+
+    async fn read_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error>;
+    async fn write_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "embedded-hal-async")]
+async fn async_i2c_read<T: embedded_hal_async::i2c::I2c>(
+    bus: &mut T,
+    device: u8,
+    register: RegisterAddress,
+    buffer: &mut [u8],
+) -> Result<(), T::Error> {
+    let (address, len) = register.encode(0);
+    bus.write_read(device, &address[..len], buffer).await
+}
+
+#[cfg(feature = "embedded-hal-async")]
+async fn async_i2c_write<T: embedded_hal_async::i2c::I2c>(
+    bus: &mut T,
+    device: u8,
+    register: RegisterAddress,
+    data: &[u8],
+) -> Result<(), T::Error> {
+    let (buffer, len) = prefixed(register.encode(0), data);
+    bus.write(device, &buffer[..len]).await
+}
+
+#[cfg(feature = "embedded-hal-async")]
+async fn async_spi_read<T: embedded_hal_async::spi::SpiDevice>(
+    bus: &mut T,
+    addressing: Addressing,
+    register: RegisterAddress,
+    buffer: &mut [u8],
+) -> Result<(), T::Error> {
+    let (address, len) = register.encode(addressing.spi_read);
+    bus.transaction(&mut [
+        spi::Operation::Write(&address[..len]),
+        spi::Operation::Read(buffer),
+    ])
+    .await
+}
+
+#[cfg(feature = "embedded-hal-async")]
+async fn async_spi_write<T: embedded_hal_async::spi::SpiDevice>(
+    bus: &mut T,
+    addressing: Addressing,
+    register: RegisterAddress,
+    data: &[u8],
+) -> Result<(), T::Error> {
+    let (address, len) = register.encode(addressing.spi_write);
+    bus.transaction(&mut [
+        spi::Operation::Write(&address[..len]),
+        spi::Operation::Write(data),
+    ])
+    .await
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl<T: embedded_hal_async::i2c::I2c> AsyncRegisterBus<I2c> for T {
+    type Error = T::Error;
+
+    async fn read_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        async_i2c_read(self, device, register, buffer).await
+    }
+
+    async fn write_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        async_i2c_write(self, device, register, data).await
+    }
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl<T: embedded_hal_async::spi::SpiDevice> AsyncRegisterBus<Spi> for T {
+    type Error = T::Error;
+
+    async fn read_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        async_spi_read(self, Addressing::DEFAULT, register, buffer).await
+    }
+
+    async fn write_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        async_spi_write(self, Addressing::DEFAULT, register, data).await
+    }
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl<T: embedded_hal_async::spi::SpiDevice> AsyncRegisterBus<Spi> for Device<T> {
+    type Error = T::Error;
+
+    async fn read_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        async_spi_read(&mut self.bus, self.addressing, register, buffer).await
+    }
+
+    async fn write_register(
+        &mut self,
+        _: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        async_spi_write(&mut self.bus, self.addressing, register, data).await
+    }
+}
@@ -0,0 +1,212 @@
+use super::{RegisterAddress, RegisterBus};
+
+/// Longest register kept in the cache, in bytes. Longer registers bypass the cache.
+const MAX_CACHED_SIZE: usize = 8;
+
+#[derive(Clone, Copy)]
+struct Entry {
+    value: [u8; MAX_CACHED_SIZE],
+    len: u8,
+    device: u8,
+    register: RegisterAddress,
+    valid: bool,
+    dirty: bool,
+}
+
+impl Entry {
+    const EMPTY: Self = Self {
+        value: [0; MAX_CACHED_SIZE],
+        len: 0,
+        device: 0,
+        register: RegisterAddress::Narrow(0),
+        valid: false,
+        dirty: false,
+    };
+
+    fn holds(&self, device: u8, register: RegisterAddress, len: usize) -> bool {
+        self.valid && self.device == device && self.register == register && self.len as usize == len
+    }
+}
+
+/// Register map cache, keeping last known values of registers `0..N` of a device.
+///
+/// Reads of cached registers don't touch the bus, writes only update the cache and mark
+/// the register dirty, until [`Cached::sync`] stores all dirty registers. Registers declared
+/// with `volatile = true` bypass the cache.
+///
+/// Registers take their bus by value, so the cache is usually shared by passing `&mut Cached`:
+/// ```ignore,rust
+/// let mut cache = Cached::<_, 16>::new(i2c);
+/// Config::new(&mut cache).write(Config::Rate, 3);
+/// Config::new(&mut cache).write(Config::Shutdown, 1);
+/// cache.sync()?;
+/// ```
+pub struct Cached<BUS, const N: usize> {
+    bus: BUS,
+    entries: [Entry; N],
+}
+
+impl<BUS, const N: usize> Cached<BUS, N> {
+    /// Fails compilation of caches longer than the 16 bit address space.
+    const FITS_ADDRESS_SPACE: () = assert!(N <= 0x1_0000, "register addresses are 16 bit long");
+
+    pub fn new(bus: BUS) -> Self {
+        let () = Self::FITS_ADDRESS_SPACE;
+
+        Self {
+            bus,
+            entries: [Entry::EMPTY; N],
+        }
+    }
+
+    /// Gives the bus back. Values of dirty registers are lost, call `sync` first.
+    pub fn release(self) -> BUS {
+        self.bus
+    }
+
+    /// Forgets all cached values, including the dirty ones.
+    pub fn invalidate(&mut self) {
+        self.entries = [Entry::EMPTY; N];
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.entries.iter().any(|entry| entry.dirty)
+    }
+
+    /// Writes all dirty registers to the bus.
+    pub fn sync<K>(&mut self) -> Result<(), BUS::Error>
+    where
+        BUS: RegisterBus<K>,
+    {
+        for entry in self.entries.iter_mut() {
+            if entry.dirty {
+                self.bus.write_register(
+                    entry.device,
+                    entry.register,
+                    &entry.value[..entry.len as usize],
+                )?;
+                entry.dirty = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry(&mut self, register: RegisterAddress, len: usize) -> Option<&mut Entry> {
+        if len > MAX_CACHED_SIZE {
+            return None;
+        }
+        self.entries.get_mut(register.get() as usize)
+    }
+}
+
+impl<K, BUS: RegisterBus<K>, const N: usize> RegisterBus<K> for Cached<BUS, N> {
+    type Error = BUS::Error;
+
+    fn read_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        match self.entry(register, buffer.len()) {
+            Some(entry) if entry.holds(device, register, buffer.len()) => {
+                buffer.copy_from_slice(&entry.value[..buffer.len()]);
+                Ok(())
+            }
+            _ => {
+                self.bus.read_register(device, register, buffer)?;
+                match self.entry(register, buffer.len()) {
+                    // Dirty value of a register accessed in a different way is kept for `sync`
+                    Some(entry) if !entry.dirty => {
+                        entry.value[..buffer.len()].copy_from_slice(buffer);
+                        entry.len = buffer.len() as u8;
+                        entry.device = device;
+                        entry.register = register;
+                        entry.valid = true;
+                    }
+                    _ => {}
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        match self.entry(register, data.len()) {
+            Some(entry) if !entry.dirty || entry.holds(device, register, data.len()) => {
+                entry.value[..data.len()].copy_from_slice(data);
+                entry.len = data.len() as u8;
+                entry.device = device;
+                entry.register = register;
+                entry.valid = true;
+                entry.dirty = true;
+                Ok(())
+            }
+            _ => self.bus.write_register(device, register, data),
+        }
+    }
+
+    fn read_volatile_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.bus.read_volatile_register(device, register, buffer)
+    }
+
+    fn write_volatile_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.bus.write_volatile_register(device, register, data)
+    }
+}
+
+impl<K, BUS: RegisterBus<K>, const N: usize> RegisterBus<K> for &mut Cached<BUS, N> {
+    type Error = BUS::Error;
+
+    fn read_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        (**self).read_register(device, register, buffer)
+    }
+
+    fn write_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        (**self).write_register(device, register, data)
+    }
+
+    fn read_volatile_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        (**self).read_volatile_register(device, register, buffer)
+    }
+
+    fn write_volatile_register(
+        &mut self,
+        device: u8,
+        register: RegisterAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        (**self).write_volatile_register(device, register, data)
+    }
+}
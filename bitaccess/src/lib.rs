@@ -87,6 +87,18 @@
 //! WriteBits or both implementations. Field can be skipped,
 //! which will result in read_write register.
 //!
+//! ### Storage
+//! Allowed options:
+//! * plain | default
+//! * atomic
+//!
+//! `storage = atomic` keeps the value in `AtomicU8`..`AtomicU64` matching `base_type`,
+//! so the register can be shared between threads, eg. as a page table entry or ring buffer descriptor.
+//! Such register implements `AtomicWriteBits` instead of `WriteBits`, which writes through `&self`
+//! using `fetch_or` / `fetch_and` or a CAS loop, and provides `compare_exchange_fields(field, current, new)`.
+//! `modify(|r| ...)` applies several `write_to_cache` calls to a snapshot returned by `fetch()`
+//! and stores them at once, retrying when the register changed in the meantime.
+//!
 //! ### Additional attributes on main enum
 //! All attributes past bitaccess will be copied to resulting **struct**
 //! (yeah, this enum transforms into struct under the hood)
@@ -105,6 +117,75 @@
 //! #### single
 //! For single bit accessors `#[bit(N)]` is allowed.
 //!
+//! #### split
+//! Fields spread over several bit ranges, like RISC-V B-type immediates, list all pieces:
+//! `#[bits(31, 7, 30..25, 11..8)]`. Pieces are concatenated from the most significant part
+//! of the value, unless `order = lsb_first` is given. Descending ranges, like `30..25`,
+//! include both ends, as in datasheets - they're accepted only among pieces of split fields.
+//! Pieces can't overlap and have to fit in `base_type`. `read` and `write` assemble and
+//! scatter the value.
+//!
+//! #### signed
+//! Two's complement fields, like temperature offsets or branch immediates, are declared with
+//! `signed`: `#[bits(4..12, signed)]`. Their type is the narrowest signed integer holding
+//! the field, unless given explicitly with `signed = i32`, which can't be narrower than the
+//! field. `read(...).get()` returns sign-extended value, while writing a value not fitting in
+//! the field panics, even when it doesn't fit in `base_type` either.
+//!
+//! #### arrays
+//! Registers holding identical fields, like GPIO modes or interrupt priorities, may declare them
+//! once with `count` and optional `stride` (distance in bits between consecutive fields,
+//! defaults to field size): `#[bits(0..2, count = 16, stride = 2)]`. Such field is
+//! a [`FieldArray`] and its elements are accessed with `Reg::Mode.at(5)`, which panics on out
+//! of bounds index (or fails compilation in const context), `try_at(5)` or `at_const::<5>()`,
+//! which checks the index during compilation.
+//!
+//! #### shifted
+//! Fields holding an aligned address without its low bits, like frame numbers of page-table
+//! entries, declare the number of dropped bits with `shift`: `#[bits(12..48, shift = 12)]`.
+//! `read` returns the full address and `write` takes one, panicking when it's not aligned to
+//! `1 << shift` (`try_write` returns `Err(`[`InvalidValue::Misaligned`]`)`). Such field holds
+//! `base_type` values, or `usize` with bare `#[ty]`, or raw pointers with eg. `#[ty(*const u8)]`.
+//! Addresses which don't fit in `base_type`, eg. above 4 GiB in `u32` register, panic as well
+//! (`try_write` returns `Err(`[`InvalidValue::TooWide`]`)`):
+//! ```ignore,rust
+//! #[bitaccess(base_type = u64, kind = read_write)]
+//! pub enum PageTableEntry {
+//!     #[bit(0)]
+//!     Present,
+//!     #[bits(12..48, shift = 12)]
+//!     #[ty]
+//!     Frame,
+//! }
+//!
+//! entry.write(PageTableEntry::Frame, 0x8020_3000usize);
+//! let frame: usize = entry.read(PageTableEntry::Frame).get();
+//! ```
+//!
+//! #### encoding
+//! Fields stored as BCD, like RTC time and date, or Gray code, like encoder positions, declare it
+//! with `encoding = bcd` or `encoding = gray`: `#[bits(0..8, encoding = bcd)]`. `read` returns
+//! the decoded integer and `write` encodes it. Writing a value with more decimal digits than
+//! a BCD field holds panics, `try_write` returns `Err(`[`InvalidValue::TooWide`]`)`. Reading
+//! never panics, digits above 9 coming from the device are decoded arithmetically, eg. `0xff`
+//! reads as 165. Check the raw register value returned by `get()` when the device may report
+//! such digits.
+//!
+//! #### fixed
+//! Fixed-point fields, like gains or PWM fractions, declare their format with `fixed`:
+//! `#[bits(0..16, fixed = "Q1.15")]` or `fixed = "UQ4.4"`. Integer bits include the sign,
+//! so the format has to match the field size. Such field holds [`Q<I, F>`](Q) or
+//! [`UQ<I, F>`](UQ), which convert from `f32` / `f64` rounding to nearest (`from_f32` returns
+//! `None` out of range, `saturating_from_f32` clamps) and back with `to_f32` / `to_f64`.
+//! `write` accepts floats, panicking when they're out of range, as well as raw bits as `base_type`.
+//! `try_write` returns `Err(`[`InvalidValue::OutOfRange`]`)` for out of range floats, with raw
+//! bits of the format's `MIN` and `MAX`:
+//! ```ignore,rust
+//! calibration.write(Calibration::Gain, -0.5);
+//! let gain: f32 = calibration.read(Calibration::Gain).get().to_f32();
+//! let raw = calibration.read(Calibration::Gain).value();
+//! ```
+//!
 //! ### Variants
 //! Fields may come in automatically cast variants (like `VariantThird` above).
 //! Bitaccess supports two ways of declaring such access:
@@ -119,6 +200,80 @@
 //! `Type` has to derive `FieldAccess` trait and specify `#[field_access(N)]` attribute,
 //! where `N` has to match `base_type` on main enum.
 //!
+//! ### UInt
+//! Plain numeric fields hold [`UInt<N>`](UInt), an unsigned integer of exactly the field width.
+//! `write` accepts it alongside raw `base_type` values, which have to fit in the field as well -
+//! writing a too wide one panics - and `read(...).get()` returns it. `UInt` can't be created
+//! from a value too wide for the field: `UInt::<8>::new(300)` returns `None`, while
+//! `UInt::<8>::new_const::<300>()` fails to compile.
+//! ```ignore,rust
+//! timer.write(Timer::Prescaler, UInt::<8>::new_const::<200>());
+//! let reload: UInt<12> = timer.read(Timer::Reload).get();
+//! ```
+//!
+//! ### Try_write
+//! Writing a raw value wider than a plain numeric field panics, while other fields, eg. ones with
+//! variants, truncate it. Writable registers without `error` provide `try_write(field, value)`,
+//! which returns `Err(`[`InvalidValue::TooWide`]`)` instead.
+//! With `checked` feature of this crate every `write` of a too wide value panics in debug builds.
+//!
+//! ### Range
+//! Numeric fields that accept only part of their bit range declare it with `range`:
+//! ```ignore,rust
+//! #[bits(0..4, range = 1..=15)]
+//! Divider,
+//! ```
+//! `write` of a value outside the range panics, `try_write` returns
+//! `Err(`[`InvalidValue::OutOfRange`]`)`. Bounds are listed in `FieldInfo::range`.
+//! Inline registers created with `new()` or `from_value(value)` aren't checked, so they may hold
+//! values outside the range, eg. zero; `try_from_value(value)` returns the same error for
+//! the first such field instead.
+//!
+//! ### Ty
+//! Fields can be read and written as native types instead of `base_type` with `#[ty(Type)]`,
+//! where `Type` is `bool`, `char` (8-bit Latin-1 character) or an unsigned integer narrower
+//! than `base_type`. Bare `#[ty]` infers the type from field width - `bool` for single bits,
+//! otherwise the narrowest unsigned integer. Types narrower than the field, like `bool` on
+//! a multi-bit field, fail compilation. Writing a `char` above `'\u{ff}'` panics, `try_write`
+//! returns `Err(`[`InvalidValue::TooWide`]`)`. `read(...).get()` returns the native value:
+//! ```ignore,rust
+//! #[bitaccess(base_type = u64, kind = read_write)]
+//! pub enum Uart {
+//!     #[bit(3)]
+//!     #[ty(bool)]
+//!     Enabled,
+//!     #[bits(8..16)]
+//!     #[ty]
+//!     Data,
+//! }
+//!
+//! uart.write(Uart::Enabled, true);
+//! let data: u8 = uart.read(Uart::Data).get();
+//! ```
+//!
+//! ### Priority
+//! With `iter_set = true` in the top level attribute, single-bit fields are gathered in
+//! `{Register}Flag` enum. `iter_set()` of a register value yields flags of all set bits, so eg.
+//! IRQ dispatch becomes a loop over pending sources. Flags are ordered by bit, unless they're
+//! marked with `#[priority(N)]` - such flags go first, lowest `N` first.
+//! ```ignore,rust
+//! #[bitaccess(base_type = u32, kind = read_only, read_via = irq_pending, iter_set = true)]
+//! pub enum IrqStatus {
+//!     #[bit(0)]
+//!     Uart,
+//!     #[bit(7)]
+//!     #[priority(0)]
+//!     Watchdog,
+//! }
+//!
+//! for irq in IrqStatus::fetch().iter_set() {
+//!     match irq {
+//!         IrqStatusFlag::Watchdog => reboot(),
+//!         IrqStatusFlag::Uart => uart_handler(),
+//!     }
+//! }
+//! ```
+//!
 //! ## Global register
 //!
 //! ```ignore,rust
@@ -146,27 +301,374 @@
 //! Global registers are created when `read_via` or `write_via` attributes are provided to bitaccess macro.
 //! All other attributes behave as in `Inline register`.
 //!
+//! Whole register can be read with `fetch()`. Readable and writable global registers also provide
+//! `modify(|r| ...)`, which reads the register once, applies all `write_to_cache` calls done
+//! in the closure and writes the result back.
+//!
+//! Readable global registers can be polled with `wait_until(field, value, max_iterations)`
+//! and `wait_while(field, value, max_iterations)`, eg. `Pll.wait_until(Pll::Locked, Locked::Yes, 1000)`.
+//! Both spin until the field reaches (or leaves) given value and return [`Timeout`] holding
+//! the last read register value when `max_iterations` reads didn't suffice.
+//! `wait_until_async` and `wait_while_async` yield to the executor between polls instead of spinning.
+//!
+//! ### Rmw
+//! Writing a single field of a readable global register reads the register first,
+//! so an interrupt between the read and the write may lose updates.
+//! `rmw` option guards such read-modify-write sequences, including `modify`:
+//! * `rmw = critical_section` - wraps them in `critical_section::with`, requires `critical-section`
+//!   feature of this crate and a critical section implementation for your target
+//! * `rmw = custom(path::to::lock)` - calls `path::to::lock(|| ...)`, where `lock` has signature
+//!   `fn<R>(f: impl FnOnce() -> R) -> R`, eg. to take an RTOS mutex
+//!
+//! ### Error
+//! Backends which can fail, like hypervisor calls or mapped files, can declare `error = Type`.
+//! Their `read_via` and `write_via` functions return `Result<base_type, Type>` and
+//! `Result<(), Type>`, while string and block forms may use `?`.
+//! Such register provides `try_read`, `try_fetch`, `try_modify`, `try_get` and `try_set`,
+//! returning the backend error, and `try_write`, which checks values like other registers do
+//! and returns [`WriteError`], either `Invalid(`[`InvalidValue`]`)` or `Backend(Type)`.
+//! `read`, `write` and others panic on errors, so `Type` has to implement `Debug`.
+//! ```ignore,rust
+//! #[bitaccess(base_type = u64, kind = read_write, error = HvError, read_via = hv_read, write_via = hv_write)]
+//! pub enum Hypervisor {
+//!     #[bits(0..8)]
+//!     Vector,
+//! }
+//!
+//! Hypervisor::new().try_write(Hypervisor::Vector, 0x20)?;
+//! ```
+//!
+//! ### Verify_writes
+//! Readable and writable global registers provide `write_verified(field, value)`, which writes
+//! the field, reads the register back and returns [`WriteMismatch`] when it doesn't hold
+//! the written value, eg. on a noisy SPI link or a register ignoring writes.
+//! Only bits of the written field are compared. Fields changed by hardware can be marked with
+//! `#[volatile]` attribute, so they are never compared. [`WriteMismatch::fields`] lists fields
+//! which didn't keep their value, using `Register::FIELDS` - names and masks of all fields.
+//!
+//! `verify_writes = true` verifies every `write`, `set` and `modify`, panicking on mismatch.
+//! It can't be used together with `error` or `bus`, whose `try_` accessors return backend errors
+//! only, such registers call `write_verified` instead.
+//! ```ignore,rust
+//! #[bitaccess(base_type = u8, kind = read_write, verify_writes = true, read_via = spi_read, write_via = spi_write)]
+//! pub enum Control {
+//!     #[bits(0..4)]
+//!     Gain,
+//!     #[bit(7)]
+//!     #[volatile]
+//!     Busy,
+//! }
+//!
+//! if let Err(mismatch) = Control::new().write_verified(Control::Gain, 0x3) {
+//!     log::warn!("{:?} not written", mismatch.fields().map(|f| f.name).collect::<Vec<_>>());
+//! }
+//! ```
+//!
+//! ### Ordering
+//! Global registers make no ordering promises by default - the compiler and CPU may move
+//! surrounding memory accesses across register reads and writes. When eg. a DMA descriptor
+//! has to be visible to the device before a doorbell register is written, use `ordering` option:
+//! * `ordering = relaxed` - default, no fences
+//! * `ordering = compiler` - `compiler_fence` before every write and after every read
+//! * `ordering = fenced` - `core::sync::atomic::fence` before every write and after every read
+//!
+//! Synchronous global registers also provide `read_fenced(field)` and `write_fenced(field, value)`,
+//! which fence a single access regardless of `ordering`, so a relaxed register can be ordered
+//! only where needed.
+//!
+//! These fences order CPU memory accesses only. They aren't I/O or DMA barriers - eg. on ARM
+//! `fence` emits `dmb ish`, not `dmb osh` or `dsb`. Targets that need such barriers have to
+//! issue them in `read_via` / `write_via`.
+//!
 //! ### Read_via | write_via
-//! Rust instructions provided within string. For some other use examples, you may check tests.
+//! Code used to access the register. Accepted forms:
+//! * string or block - Rust instructions reading into or writing from `value` variable,
+//!   eg. `read_via = { unsafe { value = GLOBAL } }`.
+//!   String form (`read_via = "..."`) is kept for backward compatibility.
+//! * function path - `read_via = path::to::read` is called as `fn() -> base_type`,
+//!   `write_via = path::to::write` is called as `fn(base_type)`.
+//! * closure - `read_via = || -> u64 { ... }` or `write_via = |v: u64| ...`,
+//!   called the same way as function paths.
+//!
+//! For some other use examples, you may check tests.
+//!
+//! ### Sysreg | csr | msr
+//! Built-in backends for registers accessed with dedicated CPU instructions.
+//! They replace `read_via` and `write_via`:
+//! * `sysreg = "daif"` - AArch64 system register, accessed with `mrs` / `msr`
+//! * `csr = 0x300` - RISC-V CSR, accessed with `csrr` / `csrw`.
+//!   Field writes use `csrc` and `csrs`, so the register isn't read before being written
+//! * `msr = 0x1B` - x86 model specific register, accessed with `rdmsr` / `wrmsr`
+//!
+//! ```ignore,rust
+//! #[bitaccess(base_type = u64, kind = read_only, sysreg = "currentel")]
+//! pub enum CurrentEl {
+//!     #[bits(2..4)]
+//!     #[variants(ExceptionLevel)]
+//!     Value,
+//! }
+//! ```
+//!
+//! Generated code uses `core::arch::asm!` for given architecture, so such registers have to be gated
+//! with `#[cfg(target_arch = "...")]` when the crate is built for multiple targets.
+//!
+//! ### Address
+//! Memory mapped registers can be declared with `address = expr`, where `expr` is address
+//! of the register. It replaces `read_via` and `write_via`; the register is accessed with
+//! `core::ptr::read_volatile` / `core::ptr::write_volatile`.
+//! Indexed registers are placed one after another, starting at `address`.
+//! ```ignore,rust
+//! #[bitaccess(base_type = u32, kind = read_write, address = 0x4000_1000)]
+//! pub enum UartControl {
+//!     #[bit(0)]
+//!     Enable,
+//! }
+//! ```
+//!
+//! ### Access_width
+//! By default `address` registers are always accessed with full `base_type` width.
+//! Registers which allow narrower transfers can declare `access_width = 8 | 16 | 32 | 64`,
+//! not wider than `base_type`. Writes of fields which fit in a single lane of such register
+//! store just that lane, without reading the register first when the field covers the lane
+//! completely. Reads, whole register writes and writes of fields spanning several lanes keep
+//! full `base_type` width.
+//!
+//! Registers which forbid full width transfers additionally declare `lanes_only = true`.
+//! All their accesses are split into lanes, and field writes store only the lanes overlapping
+//! the field.
+//!
+//! ### Endian
+//! Some peripherals have big-endian registers even on little-endian CPUs.
+//! With `endian = big` or `endian = little` global registers swap bytes of the raw value
+//! when it differs from the CPU byte order, so fields keep bit numbering from the datasheet.
+//! Works with `read_via` / `write_via` and `address`.
+//!
+//! ### Bus | device | register | register_width
+//! Registers of external chips, like sensors or PMICs, can be accessed over I2C or SPI
+//! with `embedded-hal` traits. This requires `embedded-hal` feature of this crate.
+//! * `bus = i2c, device = 0x48, register = 0x01` - register `0x01` of I2C device with address `0x48`
+//! * `bus = spi, register = 0x20` - register `0x20` of SPI device; reads set the most
+//!   significant bit of register address
+//!
+//! Such register holds the bus: it's created with `new(bus)` and gives the bus back with
+//! `release()`. Multi-byte registers are sent most significant byte first, unless
+//! `endian = little` is specified.
+//! ```ignore,rust
+//! #[bitaccess(base_type = u16, kind = read_write, bus = i2c, device = 0x48, register = 0x01)]
+//! pub enum Config {
+//!     #[bits(0..4)]
+//!     Rate,
+//!     #[bit(15)]
+//!     Shutdown,
+//! }
+//!
+//! let mut config = Config::new(i2c);
+//! config.write(Config::Shutdown, 1);
+//! let i2c = config.release();
+//! ```
+//!
+//! Register addresses are 8 bit long, chips with 16 bit ones, like EEPROMs, declare
+//! `register_width = 16`. Addresses which don't fit in the declared width fail compilation.
+//! By default SPI reads set the most significant bit of register address and SPI writes send
+//! it unchanged. SPI chips using other conventions are accessed through `bitaccess::bus::Device`,
+//! which wraps the SPI device with their `Addressing`:
+//! ```ignore,rust
+//! let addressing = Addressing { spi_read: 0, spi_write: 0x80 };
+//! let mut config = Config::new(Device::new(spi, addressing));
+//! ```
+//!
+//! Bus errors are returned by `try_` accessors, described in `Error`, other accessors panic
+//! on them. Buses other than `embedded-hal` ones can be supported by implementing
+//! `bitaccess::bus::RegisterBus`.
+//!
+//! Every field write of a bus register reads the register and writes it back.
+//! `bitaccess::bus::Cached` is a register map cache, similar to Linux regmap, which can be
+//! put between registers and the bus. It keeps last known values of registers `0..N`, reads
+//! of cached registers don't touch the bus and writes only mark registers dirty, until `sync()`
+//! stores them. Registers like status or read-to-clear ones can bypass the cache with
+//! `volatile = true`.
+//! ```ignore,rust
+//! let mut cache = Cached::<_, 16>::new(i2c);
+//! Config::new(&mut cache).write(Config::Rate, 3);
+//! Config::new(&mut cache).write(Config::Shutdown, 1);
+//! cache.sync()?;
+//! ```
+//!
+//! With `bus = async_i2c` or `bus = async_spi` the register is accessed with `embedded-hal-async`
+//! traits instead, which requires `embedded-hal-async` feature. Such register implements
+//! [`AsyncReadBits`] and [`AsyncWriteBits`], and its `get`, `set`, `fetch`, `modify`,
+//! `wait_until` and `wait_while` are `async`:
+//! ```ignore,rust
+//! config.write(Config::Shutdown, 1).await;
+//! let rate = config.read(Config::Rate).await;
+//! ```
+//!
+//! ### Index | count
+//! Families of identical global registers, like `DBGBVR<n>_EL1` or per-channel DMA registers,
+//! can be declared once with `index: T, count = N`. Instead of `new()` such register provides
+//! `at(n)`, which panics when `n >= COUNT`, and `try_at(n)` returning `Option`.
+//! Value of `index` is passed to the backend:
+//! * string or block `read_via` / `write_via` can use `index` variable
+//! * function paths and closures receive `index` as first argument,
+//!   eg. `fn(usize) -> u64` and `fn(usize, u64)`
+//! * `sysreg` replaces `<n>` placeholder in register name,
+//!   `csr` and `msr` add `index` to register address. Families of interleaved registers,
+//!   like `IA32_MTRR_PHYSBASEn` at `0x200 + 2n`, declare distance between their addresses
+//!   with `stride = N`, which defaults to 1
+//!
+//! ```ignore,rust
+//! #[bitaccess(base_type = u64, kind = read_write, index: usize, count = 16, sysreg = "dbgbvr<n>_el1")]
+//! pub enum DbgBvr {
+//!     #[bits(2..49)]
+//!     Address,
+//! }
+//!
+//! DbgBvr::at(3).write(DbgBvr::Address, 0x1000);
+//! ```
 
-use core::marker::PhantomData;
+use core::{
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    ops::{BitAnd, BitXor},
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 pub use bitaccess_macros::{bitaccess, FieldAccess};
+#[cfg(feature = "critical-section")]
+pub use critical_section;
+
+#[cfg(feature = "embedded-hal")]
+pub mod bus;
 
 pub struct FieldDefinition<B, F: FieldAccess<B>> {
     mask: B,
     _phantom: PhantomData<F>,
 }
 
+/// `N` identical fields spaced evenly within a register, eg. 16 GPIO modes.
+/// Elements are accessed with `at(index)`.
+pub struct FieldArray<B, F: FieldAccess<B>, const N: usize> {
+    masks: [B; N],
+    _phantom: PhantomData<F>,
+}
+
+/// Unsigned integer holding at most `N` bits, value type of plain numeric fields.
+/// Unlike raw `base_type` values, which are masked to field width on write, it can't be
+/// created from a value too wide for the field.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UInt<const N: u32>(u128);
+
+/// Signed fixed-point number in `Q{I}.{F}` format - `I` integer bits, including the sign,
+/// and `F` fractional bits. Value type of `#[bits(..., fixed = "Q1.15")]` fields.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Q<const I: u32, const F: u32>(i128);
+
+/// Unsigned fixed-point number in `UQ{I}.{F}` format, value type of `fixed = "UQ4.4"` fields.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UQ<const I: u32, const F: u32>(u128);
+
 pub struct Field<B, F: FieldAccess<B>> {
     value: B,
+    /// Value didn't fit in `B` when converted into the field, so writes reject it.
+    overflowed: bool,
     _phantom: PhantomData<F>,
 }
 
+/// Name and mask of a register field, listed in `Register::FIELDS`.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldInfo<B> {
+    pub name: &'static str,
+    pub mask: B,
+    /// Inclusive bounds of values allowed by `range = ...`.
+    pub range: Option<(B, B)>,
+}
+
+/// Register read back after a verified write doesn't hold the written value.
+/// Holds only the compared bits - written, non-volatile fields.
+#[derive(Clone, Copy)]
+pub struct WriteMismatch<B: 'static> {
+    expected: B,
+    actual: B,
+    fields: &'static [FieldInfo<B>],
+}
+
+/// Value written with `try_write` doesn't fit in the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueTooWide<B> {
+    pub field: &'static str,
+    pub value: B,
+    /// Largest value the field can hold.
+    pub max: B,
+}
+
+/// Value written with `try_write` lies outside `range` declared for the field, or float written
+/// to fixed-point field is out of range of its format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueOutOfRange<B> {
+    pub field: &'static str,
+    pub value: B,
+    pub min: B,
+    pub max: B,
+}
+
+/// Value written with `try_write` to a field with `shift = ...` has bits below the shift set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueMisaligned<B> {
+    pub field: &'static str,
+    pub value: B,
+    /// Alignment required by the field, `1 << shift`.
+    pub align: B,
+}
+
+/// Value rejected by `try_write`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidValue<B> {
+    TooWide(ValueTooWide<B>),
+    OutOfRange(ValueOutOfRange<B>),
+    Misaligned(ValueMisaligned<B>),
+}
+
+/// Error of `try_write` of registers with fallible backend, like `error = Type` or bus registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteError<B, E> {
+    /// Value was rejected before reaching the backend.
+    Invalid(InvalidValue<B>),
+    Backend(E),
+}
+
+/// Whether `checked` feature is enabled, so writes of too wide values panic in debug builds.
+#[doc(hidden)]
+pub const CHECKED: bool = cfg!(feature = "checked");
+
+/// Field didn't reach the awaited value within given number of polls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout<B> {
+    /// Register value read by the last poll.
+    pub last: B,
+}
+
+/// Future returned by [`yield_now`].
+pub struct YieldNow {
+    yielded: bool,
+}
+
+/// Returns `Pending` once, letting the executor run other tasks between polls of a register.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
 pub trait FieldAccess<B> {
     fn to_raw(&self) -> B;
 }
 
+/// Conversion of raw field value into the field type, used by [`Field::get`].
+/// Raw values of signed fields are already sign-extended to `B`.
+pub trait FromRaw<B> {
+    fn from_raw(raw: B) -> Self;
+}
+
 pub trait WriteBits<B, F: FieldAccess<B>> {
     fn write(&mut self, bits: FieldDefinition<B, F>, new_value: impl Into<Field<B, F>>);
 }
@@ -175,6 +677,28 @@ pub trait ReadBits<B, F: FieldAccess<B>> {
     fn read(&self, bits: FieldDefinition<B, F>) -> Field<B, F>;
 }
 
+#[allow(async_fn_in_trait)]
+pub trait AsyncWriteBits<B, F: FieldAccess<B>> {
+    async fn write(&mut self, bits: FieldDefinition<B, F>, new_value: impl Into<Field<B, F>>);
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncReadBits<B, F: FieldAccess<B>> {
+    async fn read(&mut self, bits: FieldDefinition<B, F>) -> Field<B, F>;
+}
+
+pub trait AtomicWriteBits<B, F: FieldAccess<B>> {
+    fn write(&self, bits: FieldDefinition<B, F>, new_value: impl Into<Field<B, F>>);
+    /// Stores `new` in the field if it currently holds `current`, leaving other fields untouched.
+    /// Returns previous field value, `Err` when it didn't match `current`.
+    fn compare_exchange_fields(
+        &self,
+        bits: FieldDefinition<B, F>,
+        current: impl Into<Field<B, F>>,
+        new: impl Into<Field<B, F>>,
+    ) -> Result<Field<B, F>, Field<B, F>>;
+}
+
 impl<B, F: FieldAccess<B>> FieldDefinition<B, F> {
     pub const fn new(mask: B) -> Self {
         Self {
@@ -193,19 +717,109 @@ where
     }
 }
 
+impl<const N: u32> UInt<N> {
+    pub const MAX: Self = Self(u128::MAX >> (u128::BITS - N));
+
+    /// Returns `None` when `value` doesn't fit in `N` bits.
+    pub const fn new(value: u128) -> Option<Self> {
+        if value <= Self::MAX.0 {
+            Some(Self(value))
+        } else {
+            None
+        }
+    }
+
+    /// Constant `V`, checked during compilation - `UInt::<8>::new_const::<300>()` doesn't build.
+    pub const fn new_const<const V: u128>() -> Self {
+        const { assert!(V <= Self::MAX.0, "value doesn't fit in UInt") };
+        Self(V)
+    }
+
+    pub const fn get(self) -> u128 {
+        self.0
+    }
+}
+
+impl<B, F: FieldAccess<B>, const N: usize> FieldArray<B, F, N> {
+    pub const fn new(masks: [B; N]) -> Self {
+        Self {
+            masks,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        N
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        N == 0
+    }
+}
+
+impl<B, F: FieldAccess<B>, const N: usize> FieldArray<B, F, N>
+where
+    B: Copy,
+{
+    /// Element at `index`, panics when it's out of bounds.
+    /// In const context, eg. `const MODE5: ... = Reg::Mode.at(5)`, that's a compilation error.
+    pub const fn at(&self, index: usize) -> FieldDefinition<B, F> {
+        FieldDefinition::new(self.masks[index])
+    }
+
+    /// Element at constant index `I`, checked during compilation.
+    pub const fn at_const<const I: usize>(&self) -> FieldDefinition<B, F> {
+        const { assert!(I < N, "field array index out of bounds") };
+        FieldDefinition::new(self.masks[I])
+    }
+
+    pub fn try_at(&self, index: usize) -> Option<FieldDefinition<B, F>> {
+        self.masks
+            .get(index)
+            .map(|mask| FieldDefinition::new(*mask))
+    }
+}
+
+impl<B, F: FieldAccess<B>, const N: usize> FieldArray<B, F, N>
+where
+    B: Copy + PartialEq,
+{
+    /// Whether `mask` belongs to one of the elements.
+    pub fn contains(&self, mask: B) -> bool {
+        self.masks.contains(&mask)
+    }
+}
+
 impl<B: Copy> FieldAccess<B> for B {
     fn to_raw(&self) -> B {
         *self
     }
 }
 
+impl<B: Copy> FromRaw<B> for B {
+    fn from_raw(raw: B) -> Self {
+        raw
+    }
+}
+
 impl<B, F: FieldAccess<B>> Field<B, F> {
     pub fn new(value: B) -> Self {
+        Self::overflowing(value, false)
+    }
+
+    /// Field holding `value` converted from a wider one, eg. `i32` written to `u16` register.
+    /// When the conversion `overflowed`, writes reject the field instead of storing `value`.
+    pub fn overflowing(value: B, overflowed: bool) -> Self {
         Self {
             value,
+            overflowed,
             _phantom: PhantomData,
         }
     }
+
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
 }
 
 impl<B, F: FieldAccess<B>> Field<B, F>
@@ -227,6 +841,17 @@ where
     }
 }
 
+impl<B, F: FieldAccess<B>> Field<B, F>
+where
+    B: Copy,
+    F: FromRaw<B>,
+{
+    /// Field value converted to its type, eg. sign-extended signed integer.
+    pub fn get(&self) -> F {
+        F::from_raw(self.value)
+    }
+}
+
 impl<B> From<B> for Field<B, B>
 where
     B: Copy,
@@ -234,7 +859,391 @@ where
     fn from(base: B) -> Self {
         Self {
             value: base,
+            overflowed: false,
             _phantom: Default::default(),
         }
     }
 }
+
+impl<B> WriteMismatch<B>
+where
+    B: Copy + Default + PartialEq + BitAnd<Output = B> + BitXor<Output = B>,
+{
+    pub fn new(expected: B, actual: B, fields: &'static [FieldInfo<B>]) -> Self {
+        Self {
+            expected,
+            actual,
+            fields,
+        }
+    }
+
+    pub fn expected(&self) -> B {
+        self.expected
+    }
+
+    pub fn actual(&self) -> B {
+        self.actual
+    }
+
+    /// Fields which don't hold the written value.
+    pub fn fields(&self) -> impl Iterator<Item = &'static FieldInfo<B>> {
+        let different = self.expected ^ self.actual;
+        self.fields
+            .iter()
+            .filter(move |field| field.mask & different != B::default())
+    }
+}
+
+impl<B> fmt::Debug for WriteMismatch<B>
+where
+    B: Copy + Default + PartialEq + BitAnd<Output = B> + BitXor<Output = B> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Names<'a, B: 'static>(&'a WriteMismatch<B>);
+
+        impl<B> fmt::Debug for Names<'_, B>
+        where
+            B: Copy + Default + PartialEq + BitAnd<Output = B> + BitXor<Output = B>,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_list()
+                    .entries(self.0.fields().map(|field| field.name))
+                    .finish()
+            }
+        }
+
+        f.debug_struct("WriteMismatch")
+            .field("expected", &self.expected)
+            .field("actual", &self.actual)
+            .field("fields", &Names(self))
+            .finish()
+    }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Signed fields of `#[bits(..., signed)]`, stored in two's complement.
+/// Raw values are reinterpreted as signed `$base_signed` first, so wider types keep the sign.
+/// Written values not fitting in `$base_signed` are marked overflowed, so they're rejected.
+macro_rules! signed_fields {
+    ($($base:ty as $base_signed:ty;)*) => {
+        $(
+            signed_fields!(@impl $base, $base_signed, i8, i16, i32, i64, i128);
+        )*
+    };
+    (@impl $base:ty, $base_signed:ty, $($signed:ty),*) => {
+        $(
+            impl FieldAccess<$base> for $signed {
+                fn to_raw(&self) -> $base {
+                    *self as $base
+                }
+            }
+
+            impl FromRaw<$base> for $signed {
+                fn from_raw(raw: $base) -> Self {
+                    raw as $base_signed as $signed
+                }
+            }
+
+            impl From<$signed> for Field<$base, $signed> {
+                fn from(value: $signed) -> Self {
+                    Field::overflowing(value.to_raw(), <$base_signed>::try_from(value).is_err())
+                }
+            }
+        )*
+    };
+}
+
+signed_fields! {
+    u8 as i8;
+    u16 as i16;
+    u32 as i32;
+    u64 as i64;
+    u128 as i128;
+}
+
+/// Fields of `#[ty(...)]` narrower than `base_type`. `char` fields hold 8-bit Latin-1 characters,
+/// so writes reject characters above `'\u{ff}'`.
+macro_rules! native_fields {
+    ($($base:ty => $($native:ty),*;)*) => {
+        $(
+            native_fields!(@impl $base, bool |raw| raw != 0, |_value| false);
+            native_fields!(
+                @impl $base, char |raw| char::from(raw as u8), |value| u32::from(value) > 0xff
+            );
+            $(
+                native_fields!(@impl $base, $native |raw| raw as $native, |_value| false);
+            )*
+        )*
+    };
+    (
+        @impl $base:ty, $native:ty
+        |$raw:ident| $from_raw:expr,
+        |$value:ident| $overflowed:expr
+    ) => {
+        impl FieldAccess<$base> for $native {
+            fn to_raw(&self) -> $base {
+                *self as $base
+            }
+        }
+
+        impl FromRaw<$base> for $native {
+            fn from_raw($raw: $base) -> Self {
+                $from_raw
+            }
+        }
+
+        impl From<$native> for Field<$base, $native> {
+            fn from($value: $native) -> Self {
+                Field::overflowing($value.to_raw(), $overflowed)
+            }
+        }
+    };
+}
+
+native_fields! {
+    u8 => ;
+    u16 => u8;
+    u32 => u8, u16;
+    u64 => u8, u16, u32;
+    u128 => u8, u16, u32, u64;
+}
+
+/// Fixed-point numbers, converted from floats with rounding of halves away from zero.
+macro_rules! fixed_point {
+    ($($fixed:ident($repr:ty);)*) => {
+        $(
+            impl<const I: u32, const F: u32> $fixed<I, F> {
+                pub const MIN: Self = Self(<$repr>::MIN >> (<$repr>::BITS - I - F));
+                pub const MAX: Self = Self(<$repr>::MAX >> (<$repr>::BITS - I - F));
+
+                /// Returns `None` when raw `bits` don't fit in the format.
+                pub const fn new(bits: $repr) -> Option<Self> {
+                    if bits >= Self::MIN.0 && bits <= Self::MAX.0 {
+                        Some(Self(bits))
+                    } else {
+                        None
+                    }
+                }
+
+                /// Raw bits, the value multiplied by `2^F`.
+                pub const fn get(self) -> $repr {
+                    self.0
+                }
+
+                /// Nearest representable value, or `None` when `value` is out of range or NaN.
+                pub fn from_f64(value: f64) -> Option<Self> {
+                    let scaled = value * (1u128 << F) as f64;
+                    let rounded = if scaled < 0.0 { scaled - 0.5 } else { scaled + 0.5 };
+                    if rounded > Self::MIN.0 as f64 - 1.0 && rounded < Self::MAX.0 as f64 + 1.0 {
+                        Some(Self(rounded as $repr))
+                    } else {
+                        None
+                    }
+                }
+
+                /// Nearest representable value, clamped to `MIN..=MAX`. NaN becomes zero.
+                pub fn saturating_from_f64(value: f64) -> Self {
+                    match Self::from_f64(value) {
+                        Some(fixed) => fixed,
+                        None if value.is_nan() => Self(0),
+                        None if value < 0.0 => Self::MIN,
+                        None => Self::MAX,
+                    }
+                }
+
+                pub fn from_f32(value: f32) -> Option<Self> {
+                    Self::from_f64(value as f64)
+                }
+
+                pub fn saturating_from_f32(value: f32) -> Self {
+                    Self::saturating_from_f64(value as f64)
+                }
+
+                pub fn to_f64(self) -> f64 {
+                    self.0 as f64 / (1u128 << F) as f64
+                }
+
+                pub fn to_f32(self) -> f32 {
+                    self.0 as f32 / (1u128 << F) as f32
+                }
+            }
+        )*
+    };
+}
+
+fixed_point! {
+    Q(i128);
+    UQ(u128);
+}
+
+/// Plain numeric fields, which accept both [`UInt`] and raw `base_type` values.
+/// Raw values wider than the field are marked overflowed, so writes always reject them.
+macro_rules! uint_fields {
+    ($($base:ty),*) => {
+        $(
+            impl<const N: u32> FieldAccess<$base> for UInt<N> {
+                fn to_raw(&self) -> $base {
+                    self.0 as $base
+                }
+            }
+
+            impl<const N: u32> FromRaw<$base> for UInt<N> {
+                fn from_raw(raw: $base) -> Self {
+                    Self(raw as u128)
+                }
+            }
+
+            impl<const N: u32> From<UInt<N>> for Field<$base, UInt<N>> {
+                fn from(value: UInt<N>) -> Self {
+                    Field::new(value.to_raw())
+                }
+            }
+
+            impl<const N: u32> From<$base> for Field<$base, UInt<N>> {
+                fn from(raw: $base) -> Self {
+                    Field::overflowing(raw, raw as u128 > UInt::<N>::MAX.0)
+                }
+            }
+        )*
+    };
+}
+
+uint_fields!(u8, u16, u32, u64, u128, usize);
+
+/// Address fields of `#[bits(..., shift = ...)]`, read and written as `usize` or raw pointers.
+/// Addresses not fitting in `$base`, eg. above 4 GiB in `u32` register, are marked overflowed.
+macro_rules! address_fields {
+    ($($base:ty),*) => {
+        $(
+            native_fields!(
+                @impl $base, usize |raw| raw as usize, |value| <$base>::try_from(value).is_err()
+            );
+
+            impl<T> FieldAccess<$base> for *const T {
+                fn to_raw(&self) -> $base {
+                    *self as usize as $base
+                }
+            }
+
+            impl<T> FromRaw<$base> for *const T {
+                fn from_raw(raw: $base) -> Self {
+                    raw as usize as *const T
+                }
+            }
+
+            impl<T> From<*const T> for Field<$base, *const T> {
+                fn from(value: *const T) -> Self {
+                    Field::overflowing(value.to_raw(), <$base>::try_from(value as usize).is_err())
+                }
+            }
+
+            impl<T> FieldAccess<$base> for *mut T {
+                fn to_raw(&self) -> $base {
+                    *self as usize as $base
+                }
+            }
+
+            impl<T> FromRaw<$base> for *mut T {
+                fn from_raw(raw: $base) -> Self {
+                    raw as usize as *mut T
+                }
+            }
+
+            impl<T> From<*mut T> for Field<$base, *mut T> {
+                fn from(value: *mut T) -> Self {
+                    Field::overflowing(value.to_raw(), <$base>::try_from(value as usize).is_err())
+                }
+            }
+        )*
+    };
+}
+
+address_fields!(u32, u64);
+
+/// Fixed-point fields, which accept [`Q`] / [`UQ`], floats and raw bits as `base_type`.
+/// Floats out of range of the format are saturated and marked overflowed.
+macro_rules! fixed_fields {
+    ($($base:ty),*) => {
+        $(
+            fixed_fields!(@impl $base, Q |raw| Q((raw as i128) << (i128::BITS - I - F) >> (i128::BITS - I - F)));
+            fixed_fields!(@impl $base, UQ |raw| UQ(raw as u128));
+        )*
+    };
+    (@impl $base:ty, $fixed:ident |$raw:ident| $from_raw:expr) => {
+        impl<const I: u32, const F: u32> FieldAccess<$base> for $fixed<I, F> {
+            fn to_raw(&self) -> $base {
+                (self.0 as u128 & (u128::MAX >> (u128::BITS - I - F))) as $base
+            }
+        }
+
+        impl<const I: u32, const F: u32> FromRaw<$base> for $fixed<I, F> {
+            fn from_raw($raw: $base) -> Self {
+                $from_raw
+            }
+        }
+
+        impl<const I: u32, const F: u32> From<$fixed<I, F>> for Field<$base, $fixed<I, F>> {
+            fn from(value: $fixed<I, F>) -> Self {
+                Field::new(value.to_raw())
+            }
+        }
+
+        impl<const I: u32, const F: u32> From<$base> for Field<$base, $fixed<I, F>> {
+            fn from(raw: $base) -> Self {
+                Field::new(raw)
+            }
+        }
+
+        impl<const I: u32, const F: u32> From<f64> for Field<$base, $fixed<I, F>> {
+            fn from(value: f64) -> Self {
+                let fixed = $fixed::<I, F>::saturating_from_f64(value);
+                Field::overflowing(fixed.to_raw(), $fixed::<I, F>::from_f64(value).is_none())
+            }
+        }
+
+        impl<const I: u32, const F: u32> From<f32> for Field<$base, $fixed<I, F>> {
+            fn from(value: f32) -> Self {
+                (value as f64).into()
+            }
+        }
+    };
+}
+
+fixed_fields!(u8, u16, u32, u64, u128, usize);
+
+impl<B> From<ValueTooWide<B>> for InvalidValue<B> {
+    fn from(too_wide: ValueTooWide<B>) -> Self {
+        InvalidValue::TooWide(too_wide)
+    }
+}
+
+impl<B> From<ValueOutOfRange<B>> for InvalidValue<B> {
+    fn from(out_of_range: ValueOutOfRange<B>) -> Self {
+        InvalidValue::OutOfRange(out_of_range)
+    }
+}
+
+impl<B> From<ValueMisaligned<B>> for InvalidValue<B> {
+    fn from(misaligned: ValueMisaligned<B>) -> Self {
+        InvalidValue::Misaligned(misaligned)
+    }
+}
+
+impl<B, E> From<InvalidValue<B>> for WriteError<B, E> {
+    fn from(invalid: InvalidValue<B>) -> Self {
+        WriteError::Invalid(invalid)
+    }
+}
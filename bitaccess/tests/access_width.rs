@@ -0,0 +1,80 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+// Don't do this at home
+static mut BYTE_LANES: u32 = 0;
+static mut BIG_ENDIAN_LANES: u32 = 0;
+static mut WORD_LANES: [u64; 2] = [0; 2];
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    access_width = 8,
+    address = core::ptr::addr_of_mut!(crate::BYTE_LANES)
+)]
+pub enum ByteLanes {
+    #[bits(8..16)]
+    Lane,
+    #[bits(20..24)]
+    Nibble,
+    #[bits(4..12)]
+    Straddling,
+}
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    endian = big,
+    access_width = 16,
+    address = core::ptr::addr_of_mut!(crate::BIG_ENDIAN_LANES)
+)]
+pub enum BigEndianLanes {
+    #[bits(0..8)]
+    Low,
+}
+
+#[bitaccess(
+    base_type = u64,
+    kind = read_write,
+    access_width = 32,
+    lanes_only = true,
+    index: usize,
+    count = 2,
+    address = core::ptr::addr_of_mut!(crate::WORD_LANES)
+)]
+pub enum WordLanes {
+    #[bits(16..48)]
+    Middle,
+}
+
+#[test]
+fn field_writes_touch_only_covered_lanes() {
+    unsafe { BYTE_LANES = 0x1122_3344 };
+    ByteLanes.write(ByteLanes::Lane, 0xab);
+    assert_eq!(unsafe { BYTE_LANES }, 0x1122_ab44);
+
+    ByteLanes.write(ByteLanes::Nibble, 0xf);
+    assert_eq!(unsafe { BYTE_LANES }, 0x11f2_ab44);
+
+    ByteLanes.write(ByteLanes::Straddling, 0x00);
+    assert_eq!(unsafe { BYTE_LANES }, 0x11f2_a004);
+    assert_eq!(ByteLanes.read(ByteLanes::Lane).value(), 0xa0);
+}
+
+#[test]
+fn lanes_follow_register_byte_order() {
+    unsafe { BIG_ENDIAN_LANES = 0xffff_ffffu32.to_be() };
+    BigEndianLanes.write(BigEndianLanes::Low, 0x12);
+    assert_eq!(unsafe { BIG_ENDIAN_LANES }, 0xffff_ff12u32.to_be());
+    assert_eq!(BigEndianLanes.read(BigEndianLanes::Low).value(), 0x12);
+}
+
+#[test]
+fn wide_registers_are_accessed_with_narrow_transfers() {
+    let mut second = WordLanes::at(1);
+    second.write(WordLanes::Middle, 0xdead_beef);
+    assert_eq!(unsafe { WORD_LANES }, [0, 0x0000_dead_beef_0000]);
+    assert_eq!(second.read(WordLanes::Middle).value(), 0xdead_beef);
+
+    second.set(u64::MAX);
+    assert_eq!(unsafe { WORD_LANES }, [0, u64::MAX]);
+}
@@ -0,0 +1,33 @@
+#![cfg(target_arch = "x86_64")]
+
+use bitaccess::bitaccess;
+
+// Reading this register requires ring 0, this test only checks that the generated asm compiles.
+#[bitaccess(base_type = u64, kind = read_write, msr = 0x1B)]
+pub enum ApicBase {
+    #[bit(8)]
+    Bsp,
+    #[bit(11)]
+    Enable,
+    #[bits(12..36)]
+    Base,
+}
+
+#[test]
+fn msr_register_compiles() {
+    let _ = ApicBase::new();
+}
+
+// IA32_MTRR_PHYSBASEn and IA32_MTRR_PHYSMASKn interleave, so each family skips every other MSR
+#[bitaccess(base_type = u64, kind = read_write, msr = 0x200, index: u32, count = 8, stride = 2)]
+pub enum MtrrPhysBase {
+    #[bits(0..8)]
+    Type,
+    #[bits(12..52)]
+    PhysBase,
+}
+
+#[test]
+fn indexed_msr_register_compiles() {
+    let _ = MtrrPhysBase::at(7);
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "embedded-hal-async")]
+
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use bitaccess::{bitaccess, AsyncReadBits, AsyncWriteBits};
+use embedded_hal_mock::eh1::{
+    i2c::{Mock as I2cMock, Transaction as I2cTransaction},
+    spi::{Mock as SpiMock, Transaction as SpiTransaction},
+};
+
+#[bitaccess(base_type = u16, kind = read_write, bus = async_i2c, device = 0x48, register = 0x01)]
+pub enum Config {
+    #[bits(0..4)]
+    Rate,
+    #[bit(15)]
+    Shutdown,
+}
+
+#[bitaccess(base_type = u8, kind = write_only, bus = async_spi, register = 0x10)]
+pub enum Command {
+    #[bits(0..7)]
+    Opcode,
+}
+
+// Mocks complete immediately, so futures never have to be woken up
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn reads_and_writes_over_async_i2c() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0xa3]),
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0xa3]),
+        I2cTransaction::write(0x48, vec![0x01, 0x80, 0xa3]),
+    ]);
+    let mut config = Config::new(i2c);
+
+    block_on(async {
+        assert_eq!(config.read(Config::Rate).await.value(), 0x3);
+        config.write(Config::Shutdown, 1).await;
+    });
+    config.release().done();
+}
+
+#[test]
+fn modifies_over_async_i2c() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0xa3]),
+        I2cTransaction::write(0x48, vec![0x01, 0x80, 0xa7]),
+    ]);
+    let mut config = Config::new(i2c);
+
+    block_on(config.modify(|r| {
+        r.write_to_cache(Config::Shutdown, 1);
+        r.write_to_cache(Config::Rate, 7);
+    }));
+    config.release().done();
+}
+
+#[test]
+fn writes_over_async_spi() {
+    let spi = SpiMock::new(&[
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(0x10),
+        SpiTransaction::write_vec(vec![0x1f]),
+        SpiTransaction::transaction_end(),
+    ]);
+    let mut command = Command::new(spi);
+
+    block_on(command.write(Command::Opcode, 0x1f));
+    command.release().done();
+}
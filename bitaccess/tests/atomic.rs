@@ -0,0 +1,100 @@
+use std::{sync::Arc, thread};
+
+use bitaccess::{bitaccess, AtomicWriteBits, ReadBits};
+
+#[bitaccess(base_type = u64, kind = read_write, storage = atomic)]
+pub enum Descriptor {
+    #[bit(0)]
+    Owned,
+    #[bit(1)]
+    Ready,
+    #[bits(8..16)]
+    #[variants(Idle => 0, Busy => 1, Done => 2)]
+    State,
+    #[bits(16..32)]
+    Length,
+}
+
+#[test]
+fn reads_and_writes_through_shared_reference() {
+    let d = Descriptor::new();
+    d.write(Descriptor::Owned, 1);
+    d.write(Descriptor::Length, 0x1234);
+    d.write(Descriptor::State, State::Busy);
+
+    assert_eq!(d.read(Descriptor::Owned).value(), 1);
+    assert_eq!(d.read(Descriptor::Length).value(), 0x1234);
+    assert_eq!(d.read(Descriptor::State).variant(), State::Busy);
+
+    d.write(Descriptor::Owned, 0);
+    assert_eq!(d.get(), 0x1234_0100);
+}
+
+#[test]
+fn modifies_several_fields_at_once() {
+    let d = Descriptor::from_value(0x10_0000);
+    d.modify(|r| {
+        r.write_to_cache(Descriptor::Ready, 1);
+        r.write_to_cache(Descriptor::State, State::Done);
+    });
+
+    assert_eq!(d.get(), 0x10_0202);
+    assert_eq!(d.fetch().read(Descriptor::Length).value(), 0x10);
+}
+
+#[test]
+fn compare_exchanges_single_field() {
+    let d = Descriptor::from_value(0xff_0000);
+
+    assert_eq!(
+        d.compare_exchange_fields(Descriptor::State, State::Idle, State::Busy)
+            .map(|field| field.variant())
+            .map_err(|field| field.variant()),
+        Ok(State::Idle)
+    );
+    assert_eq!(
+        d.compare_exchange_fields(Descriptor::State, State::Idle, State::Done)
+            .map(|field| field.variant())
+            .map_err(|field| field.variant()),
+        Err(State::Busy)
+    );
+    assert_eq!(d.get(), 0xff_0100);
+}
+
+#[test]
+fn concurrent_writes_dont_lose_updates() {
+    let d = Arc::new(Descriptor::new());
+    let handles: Vec<_> = [Descriptor::Owned, Descriptor::Ready]
+        .into_iter()
+        .map(|field| {
+            let d = Arc::clone(&d);
+            let mask = field.mask();
+            thread::spawn(move || {
+                for i in 0..10_000u64 {
+                    d.write(bitaccess::FieldDefinition::<u64, u64>::new(mask), i % 2);
+                }
+                d.write(field, 1);
+            })
+        })
+        .collect();
+    let counter = {
+        let d = Arc::clone(&d);
+        thread::spawn(move || {
+            for _ in 0..10_000 {
+                d.modify(|r| {
+                    let length = r.read(Descriptor::Length).value();
+                    r.write_to_cache(Descriptor::Length, length + 1);
+                });
+            }
+        })
+    };
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    counter.join().unwrap();
+
+    assert_eq!(d.read(Descriptor::Owned).value(), 1);
+    assert_eq!(d.read(Descriptor::Ready).value(), 1);
+    assert_eq!(d.read(Descriptor::Length).value(), 10_000);
+}
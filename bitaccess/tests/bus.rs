@@ -0,0 +1,188 @@
+#![cfg(feature = "embedded-hal")]
+
+use bitaccess::{
+    bitaccess,
+    bus::{Addressing, Device},
+    InvalidValue,
+    ReadBits,
+    ValueTooWide,
+    WriteBits,
+    WriteError,
+};
+use embedded_hal::i2c::ErrorKind;
+use embedded_hal_mock::eh1::{
+    i2c::{Mock as I2cMock, Transaction as I2cTransaction},
+    spi::{Mock as SpiMock, Transaction as SpiTransaction},
+};
+
+#[bitaccess(base_type = u16, kind = read_write, bus = i2c, device = 0x48, register = 0x01)]
+pub enum Config {
+    #[bits(0..4)]
+    Rate,
+    #[bit(15)]
+    Shutdown,
+}
+
+#[bitaccess(base_type = u16, kind = read_only, bus = i2c, device = 0x48, register = 0x00)]
+pub enum Temperature {
+    #[bits(4..16)]
+    Value,
+}
+
+#[bitaccess(base_type = u32, kind = read_write, endian = little, bus = spi, register = 0x20)]
+pub enum Control {
+    #[bits(0..8)]
+    Low,
+    #[bits(24..32)]
+    High,
+}
+
+#[bitaccess(
+    base_type = u16,
+    kind = read_write,
+    bus = i2c,
+    device = 0x50,
+    register = 0x1234,
+    register_width = 16
+)]
+pub enum Eeprom {
+    #[bits(0..8)]
+    Low,
+}
+
+#[bitaccess(base_type = u8, kind = read_write, bus = spi, register = 0x0f)]
+pub enum Status {
+    #[bits(0..4)]
+    Mode,
+}
+
+#[test]
+fn reads_big_endian_register_over_i2c() {
+    let i2c = I2cMock::new(&[I2cTransaction::write_read(
+        0x48,
+        vec![0x00],
+        vec![0x19, 0x30],
+    )]);
+    let temperature = Temperature::new(i2c);
+
+    assert_eq!(temperature.read(Temperature::Value).value(), 0x193);
+    temperature.release().done();
+}
+
+#[test]
+fn writes_field_over_i2c() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0xa3]),
+        I2cTransaction::write(0x48, vec![0x01, 0x80, 0xa3]),
+    ]);
+    let mut config = Config::new(i2c);
+
+    config.write(Config::Shutdown, 1);
+    config.release().done();
+}
+
+#[test]
+fn modifies_register_over_i2c_with_single_write() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0xa3]),
+        I2cTransaction::write(0x48, vec![0x01, 0x80, 0xa7]),
+    ]);
+    let mut config = Config::new(i2c);
+
+    config.modify(|r| {
+        r.write_to_cache(Config::Shutdown, 1);
+        r.write_to_cache(Config::Rate, 7);
+    });
+    config.release().done();
+}
+
+#[test]
+fn rejects_invalid_values_without_touching_the_bus() {
+    let i2c = I2cMock::new(&[]);
+    let mut config = Config::new(i2c);
+
+    assert_eq!(
+        config.try_write(Config::Rate, 0x10),
+        Err(WriteError::Invalid(InvalidValue::TooWide(ValueTooWide {
+            field: "Rate",
+            value: 0x10,
+            max: 0xf
+        })))
+    );
+    config.release().done();
+}
+
+#[test]
+fn accesses_little_endian_register_over_spi() {
+    let spi = SpiMock::new(&[
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(0xa0),
+        SpiTransaction::read_vec(vec![0x11, 0x22, 0x33, 0x44]),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(0x20),
+        SpiTransaction::write_vec(vec![0x11, 0x22, 0x33, 0xab]),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(0xa0),
+        SpiTransaction::read_vec(vec![0x11, 0x22, 0x33, 0xab]),
+        SpiTransaction::transaction_end(),
+    ]);
+    let mut control = Control::new(spi);
+
+    control.write(Control::High, 0xab);
+    let fetched = control.fetch();
+    assert_eq!(fetched.read(Control::Low).value(), 0x11);
+    assert_eq!(fetched.read(Control::High).value(), 0xab);
+    control.release().done();
+}
+
+#[test]
+fn propagates_bus_errors() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x00], vec![0x00, 0x00]).with_error(ErrorKind::Other),
+    ]);
+    let temperature = Temperature::new(i2c);
+
+    assert_eq!(
+        temperature.try_read(Temperature::Value).map(|f| f.value()),
+        Err(ErrorKind::Other)
+    );
+    temperature.release().done();
+}
+
+#[test]
+fn sends_wide_register_address_over_i2c() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x50, vec![0x12, 0x34], vec![0x01, 0x5a]),
+        I2cTransaction::write_read(0x50, vec![0x12, 0x34], vec![0x01, 0x5a]),
+        I2cTransaction::write(0x50, vec![0x12, 0x34, 0x01, 0xa5]),
+    ]);
+    let mut eeprom = Eeprom::new(i2c);
+
+    assert_eq!(eeprom.read(Eeprom::Low).value(), 0x5a);
+    eeprom.write(Eeprom::Low, 0xa5);
+    eeprom.release().done();
+}
+
+#[test]
+fn sets_custom_spi_flags() {
+    let spi = SpiMock::new(&[
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(0x0f),
+        SpiTransaction::read(0x30),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(0x8f),
+        SpiTransaction::write_vec(vec![0x35]),
+        SpiTransaction::transaction_end(),
+    ]);
+    let addressing = Addressing {
+        spi_read: 0,
+        spi_write: 0x80,
+    };
+    let mut status = Status::new(Device::new(spi, addressing));
+
+    status.write(Status::Mode, 5);
+    status.release().release().done();
+}
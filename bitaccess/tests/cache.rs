@@ -0,0 +1,71 @@
+#![cfg(feature = "embedded-hal")]
+
+use bitaccess::{bitaccess, bus::Cached, ReadBits, WriteBits};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+#[bitaccess(base_type = u16, kind = read_write, bus = i2c, device = 0x48, register = 0x01)]
+pub enum Config {
+    #[bits(0..4)]
+    Rate,
+    #[bit(15)]
+    Shutdown,
+}
+
+#[bitaccess(
+    base_type = u8,
+    kind = read_only,
+    bus = i2c,
+    device = 0x48,
+    register = 0x02,
+    volatile = true
+)]
+pub enum Status {
+    #[bit(0)]
+    Ready,
+}
+
+#[test]
+fn writes_are_deferred_until_sync() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0xa3]),
+        I2cTransaction::write(0x48, vec![0x01, 0x80, 0xa7]),
+    ]);
+    let mut cache = Cached::<_, 4>::new(i2c);
+
+    Config::new(&mut cache).write(Config::Shutdown, 1);
+    Config::new(&mut cache).write(Config::Rate, 7);
+    assert_eq!(Config::new(&mut cache).read(Config::Rate).value(), 7);
+    assert!(cache.is_dirty());
+
+    cache.sync().unwrap();
+    assert!(!cache.is_dirty());
+    cache.release().done();
+}
+
+#[test]
+fn volatile_registers_bypass_cache() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x02], vec![0x00]),
+        I2cTransaction::write_read(0x48, vec![0x02], vec![0x01]),
+    ]);
+    let mut cache = Cached::<_, 4>::new(i2c);
+
+    assert_eq!(Status::new(&mut cache).read(Status::Ready).value(), 0);
+    assert_eq!(Status::new(&mut cache).read(Status::Ready).value(), 1);
+    cache.release().done();
+}
+
+#[test]
+fn invalidated_registers_are_read_again() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0x03]),
+        I2cTransaction::write_read(0x48, vec![0x01], vec![0x00, 0x05]),
+    ]);
+    let mut cache = Cached::<_, 4>::new(i2c);
+
+    assert_eq!(Config::new(&mut cache).read(Config::Rate).value(), 3);
+    assert_eq!(Config::new(&mut cache).read(Config::Rate).value(), 3);
+    cache.invalidate();
+    assert_eq!(Config::new(&mut cache).read(Config::Rate).value(), 5);
+    cache.release().done();
+}
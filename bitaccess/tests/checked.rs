@@ -0,0 +1,25 @@
+#![cfg(all(feature = "checked", debug_assertions))]
+
+use bitaccess::{bitaccess, WriteBits};
+
+#[bitaccess(base_type = u16, kind = read_write)]
+pub enum Pll {
+    #[bits(0..6)]
+    Divider,
+    #[bits(6..16)]
+    Multiplier,
+}
+
+#[test]
+fn accepts_fitting_values() {
+    let mut pll = Pll::new();
+    pll.write(Pll::Divider, 0x3f);
+
+    assert_eq!(pll.get(), 0x3f);
+}
+
+#[test]
+#[should_panic(expected = "ValueTooWide { field: \"Divider\", value: 64, max: 63 }")]
+fn panics_on_too_wide_values() {
+    Pll::new().write(Pll::Divider, 0x40);
+}
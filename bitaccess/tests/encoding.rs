@@ -0,0 +1,77 @@
+use bitaccess::{bitaccess, InvalidValue, ReadBits, ValueTooWide, WriteBits};
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum RtcTime {
+    #[bits(0..8, encoding = bcd)]
+    Seconds,
+    #[bits(8..16, encoding = bcd)]
+    #[ty]
+    Minutes,
+    #[bits(16..22, encoding = gray)]
+    Position,
+}
+
+#[test]
+fn decodes_bcd() {
+    let mut time = RtcTime::new();
+
+    time.write(RtcTime::Seconds, 59);
+    time.write(RtcTime::Minutes, 7u8);
+    assert_eq!(time.get(), 0x0759);
+    assert_eq!(time.read(RtcTime::Seconds).value(), 59);
+
+    let minutes: u8 = time.read(RtcTime::Minutes).get();
+    assert_eq!(minutes, 7);
+}
+
+#[test]
+#[should_panic(expected = "value out of range of BCD field Seconds")]
+fn write_panics_for_too_many_digits() {
+    let mut time = RtcTime::new();
+
+    time.write(RtcTime::Seconds, 100);
+}
+
+#[test]
+fn try_write_rejects_too_many_digits() {
+    let mut time = RtcTime::new();
+
+    assert_eq!(
+        time.try_write(RtcTime::Seconds, 100),
+        Err(InvalidValue::TooWide(ValueTooWide {
+            field: "Seconds",
+            value: 100,
+            max: 99
+        }))
+    );
+    assert_eq!(time.try_write(RtcTime::Seconds, 99), Ok(()));
+    assert_eq!(time.get(), 0x99);
+}
+
+#[test]
+fn decodes_invalid_digits_arithmetically() {
+    let time = RtcTime::from_value(0x5a);
+
+    assert_eq!(time.read(RtcTime::Seconds).value(), 60);
+    assert_eq!(
+        RtcTime::from_value(0xff).read(RtcTime::Seconds).value(),
+        165
+    );
+}
+
+#[test]
+fn decodes_gray_code() {
+    let mut time = RtcTime::new();
+
+    for position in 0..64 {
+        time.write(RtcTime::Position, position);
+        assert_eq!(time.read(RtcTime::Position).value(), position);
+    }
+
+    time.write(RtcTime::Position, 5);
+    assert_eq!(time.get(), 0b111 << 16);
+    assert!(matches!(
+        time.try_write(RtcTime::Position, 64),
+        Err(InvalidValue::TooWide(_))
+    ));
+}
@@ -0,0 +1,76 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+// Don't do this at home
+static mut BIG_ENDIAN_TEST: u32 = 0;
+static mut LITTLE_ENDIAN_TEST: u32 = 0;
+static mut MMIO_TEST: [u16; 4] = [0; 4];
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    endian = big,
+    read_via = { unsafe { value = crate::BIG_ENDIAN_TEST } },
+    write_via = { unsafe { crate::BIG_ENDIAN_TEST = value } }
+)]
+pub enum BigEndian {
+    #[bits(0..8)]
+    Low,
+    #[bits(24..32)]
+    High,
+}
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    endian = little,
+    read_via = { unsafe { value = crate::LITTLE_ENDIAN_TEST } },
+    write_via = { unsafe { crate::LITTLE_ENDIAN_TEST = value } }
+)]
+pub enum LittleEndian {
+    #[bits(0..8)]
+    Low,
+}
+
+#[bitaccess(
+    base_type = u16,
+    kind = read_write,
+    endian = big,
+    index: usize,
+    count = 4,
+    address = core::ptr::addr_of_mut!(crate::MMIO_TEST)
+)]
+pub enum MmioControl {
+    #[bits(0..4)]
+    Mode,
+    #[bit(15)]
+    Enable,
+}
+
+#[test]
+fn big_endian_register_keeps_logical_bit_numbering() {
+    BigEndian.write(BigEndian::Low, 0x12);
+    assert_eq!(unsafe { BIG_ENDIAN_TEST }, 0x12u32.to_be());
+    assert_eq!(BigEndian.read(BigEndian::Low).value(), 0x12);
+
+    unsafe { BIG_ENDIAN_TEST = u32::from_ne_bytes([0xab, 0, 0, 0x12]) };
+    assert_eq!(BigEndian.read(BigEndian::High).value(), 0xab);
+    assert_eq!(BigEndian.read(BigEndian::Low).value(), 0x12);
+}
+
+#[test]
+fn little_endian_register_is_stored_as_little_endian() {
+    LittleEndian.write(LittleEndian::Low, 0x34);
+    assert_eq!(unsafe { LITTLE_ENDIAN_TEST.to_ne_bytes() }, [0x34, 0, 0, 0]);
+    assert_eq!(LittleEndian.read(LittleEndian::Low).value(), 0x34);
+}
+
+#[test]
+fn address_register_swaps_bytes() {
+    let mut control = MmioControl::at(2);
+    control.write(MmioControl::Enable, 1);
+    control.write(MmioControl::Mode, 0x5);
+    assert_eq!(
+        unsafe { MMIO_TEST },
+        [0, 0, u16::from_ne_bytes([0x80, 0x05]), 0]
+    );
+}
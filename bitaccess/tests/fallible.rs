@@ -0,0 +1,141 @@
+use bitaccess::{bitaccess, InvalidValue, ReadBits, ValueTooWide, WriteBits, WriteError};
+
+#[derive(Debug, PartialEq)]
+pub enum HvError {
+    Denied,
+}
+
+// Don't do this at home
+static mut HV_REGISTER: u64 = 0;
+static mut HV_DENIED: bool = false;
+
+fn hv_read() -> Result<u64, HvError> {
+    unsafe {
+        if HV_DENIED {
+            Err(HvError::Denied)
+        } else {
+            Ok(HV_REGISTER)
+        }
+    }
+}
+
+fn hv_write(value: u64) -> Result<(), HvError> {
+    unsafe {
+        if HV_DENIED {
+            Err(HvError::Denied)
+        } else {
+            HV_REGISTER = value;
+            Ok(())
+        }
+    }
+}
+
+#[bitaccess(
+    base_type = u64,
+    kind = read_write,
+    error = crate::HvError,
+    read_via = crate::hv_read,
+    write_via = crate::hv_write
+)]
+pub enum Hypervisor {
+    #[bits(0..8)]
+    Vector,
+    #[bit(63)]
+    Enabled,
+}
+
+// Don't do this at home
+static mut FILE_REGISTER: Option<u32> = None;
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_only,
+    error = crate::HvError,
+    read_via = { value = unsafe { crate::FILE_REGISTER }.ok_or(crate::HvError::Denied)? }
+)]
+pub enum Mapped {
+    #[bits(0..4)]
+    Low,
+}
+
+#[bitaccess(
+    base_type = u32,
+    kind = write_only,
+    error = crate::HvError,
+    write_via = { Err(crate::HvError::Denied)? }
+)]
+pub enum Locked {
+    #[bits(0..4)]
+    Low,
+}
+
+#[test]
+fn propagates_backend_errors() {
+    unsafe {
+        HV_REGISTER = 0;
+        HV_DENIED = false;
+    }
+    let mut hv = Hypervisor::new();
+
+    assert_eq!(hv.try_write(Hypervisor::Vector, 0x20), Ok(()));
+    assert_eq!(hv.try_read(Hypervisor::Vector).map(|f| f.value()), Ok(0x20));
+    assert_eq!(
+        hv.try_modify(|r| r.write_to_cache(Hypervisor::Enabled, 1)),
+        Ok(())
+    );
+    assert_eq!(
+        Hypervisor::try_fetch().map(|r| r.get()),
+        Ok(0x8000_0000_0000_0020)
+    );
+    assert_eq!(hv.read(Hypervisor::Enabled).value(), 1);
+
+    unsafe { HV_DENIED = true };
+    assert_eq!(
+        hv.try_write(Hypervisor::Vector, 0x21),
+        Err(WriteError::Backend(HvError::Denied))
+    );
+    assert_eq!(hv.try_set(0), Err(HvError::Denied));
+    assert!(Hypervisor::try_fetch().is_err());
+    unsafe { HV_DENIED = false };
+    assert_eq!(hv.get(), 0x8000_0000_0000_0020);
+}
+
+#[test]
+fn propagates_errors_from_statements() {
+    unsafe { FILE_REGISTER = None };
+    assert!(Mapped.try_read(Mapped::Low).is_err());
+
+    unsafe { FILE_REGISTER = Some(0x5) };
+    assert_eq!(Mapped.try_read(Mapped::Low).map(|f| f.value()), Ok(0x5));
+}
+
+#[test]
+#[should_panic(expected = "failed to read register: Denied")]
+fn panics_on_errors_in_infallible_api() {
+    unsafe { FILE_REGISTER = None };
+    Mapped.read(Mapped::Low);
+}
+
+#[test]
+#[should_panic(expected = "failed to write register: Denied")]
+fn keeps_error_in_panic_message() {
+    Locked.write(Locked::Low, 1);
+}
+
+#[test]
+fn reports_invalid_values_apart_from_backend_errors() {
+    let mut locked = Locked;
+
+    assert_eq!(
+        locked.try_write(Locked::Low, 0x10),
+        Err(WriteError::Invalid(InvalidValue::TooWide(ValueTooWide {
+            field: "Low",
+            value: 0x10,
+            max: 0xf
+        })))
+    );
+    assert_eq!(
+        locked.try_write(Locked::Low, 0xf),
+        Err(WriteError::Backend(HvError::Denied))
+    );
+}
@@ -0,0 +1,50 @@
+use bitaccess::{bitaccess, FieldDefinition, ReadBits, WriteBits};
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum GpioMode {
+    #[bits(0..2, count = 16)]
+    #[variants(Input => 0b00, Output => 0b01, Alternate => 0b10, Analog => 0b11)]
+    Mode,
+}
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum Priority {
+    #[bits(4..8, count = 4, stride = 8)]
+    Level,
+    #[bit(3)]
+    Lock,
+}
+
+const MODE5: FieldDefinition<u32, Mode> = GpioMode::Mode.at(5);
+
+#[test]
+fn accesses_elements() {
+    let mut gpio = GpioMode::new();
+    gpio.write(MODE5, Mode::Output);
+    gpio.write(GpioMode::Mode.at(15), Mode::Analog);
+    gpio.write(GpioMode::Mode.at_const::<0>(), Mode::Alternate);
+
+    assert_eq!(gpio.get(), 0xc000_0402);
+    assert_eq!(gpio.read(GpioMode::Mode.at(5)).variant(), Mode::Output);
+    assert_eq!(gpio.read(GpioMode::Mode.at(1)).variant(), Mode::Input);
+    assert_eq!(GpioMode::Mode.len(), 16);
+}
+
+#[test]
+fn respects_stride() {
+    let mut priority = Priority::new();
+    priority.write(Priority::Level.at(2), 0xa);
+    priority.write(Priority::Lock, 1);
+
+    assert_eq!(priority.get(), 0x00a0_0008);
+    assert_eq!(priority.read(Priority::Level.at(2)).value(), 0xa);
+    assert_eq!(priority.read(Priority::Level.at(3)).value(), 0);
+    assert!(Priority::Level.try_at(4).is_none());
+}
+
+#[test]
+#[should_panic]
+fn checks_bounds() {
+    let index = std::hint::black_box(4);
+    Priority::Level.at(index);
+}
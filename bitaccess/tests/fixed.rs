@@ -0,0 +1,86 @@
+use bitaccess::{bitaccess, InvalidValue, ReadBits, ValueOutOfRange, WriteBits, Q, UQ};
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum Calibration {
+    #[bits(0..16, fixed = "Q1.15")]
+    Gain,
+    #[bits(16..24, fixed = "UQ4.4")]
+    Duty,
+}
+
+#[test]
+fn converts_floats() {
+    let mut calibration = Calibration::new();
+
+    calibration.write(Calibration::Gain, -0.5);
+    calibration.write(Calibration::Duty, 2.75f32);
+    assert_eq!(calibration.get(), 0x2c_c000);
+    assert_eq!(calibration.read(Calibration::Gain).get().to_f64(), -0.5);
+    assert_eq!(calibration.read(Calibration::Duty).get().to_f32(), 2.75);
+}
+
+#[test]
+fn rounds_to_nearest() {
+    assert_eq!(UQ::<4, 4>::from_f64(0.03).map(UQ::get), Some(0));
+    assert_eq!(UQ::<4, 4>::from_f64(0.04).map(UQ::get), Some(1));
+    assert_eq!(Q::<1, 15>::from_f32(-0.00002).map(Q::get), Some(-1));
+    assert_eq!(UQ::<4, 4>::from_f64(-0.01).map(UQ::get), Some(0));
+}
+
+#[test]
+fn checks_saturation() {
+    assert_eq!(Q::<1, 15>::from_f64(1.0), None);
+    assert_eq!(Q::<1, 15>::from_f64(-1.0), Some(Q::MIN));
+    assert_eq!(UQ::<4, 4>::from_f64(-0.1), None);
+    assert_eq!(UQ::<4, 4>::from_f64(f64::NAN), None);
+    assert_eq!(Q::<1, 15>::saturating_from_f64(3.0), Q::MAX);
+    assert_eq!(Q::<1, 15>::saturating_from_f64(-3.0).get(), -0x8000);
+    assert_eq!(UQ::<4, 4>::saturating_from_f32(16.0).get(), 0xff);
+}
+
+#[test]
+#[should_panic(expected = "value out of range of fixed-point field")]
+fn write_panics_out_of_range() {
+    let mut calibration = Calibration::new();
+
+    calibration.write(Calibration::Duty, 16.0);
+}
+
+#[test]
+fn try_write_rejects_out_of_range() {
+    let mut calibration = Calibration::new();
+
+    assert_eq!(
+        calibration.try_write(Calibration::Gain, 1.0),
+        Err(InvalidValue::OutOfRange(ValueOutOfRange {
+            field: "Gain",
+            value: 0x7fff,
+            min: 0x8000,
+            max: 0x7fff
+        }))
+    );
+    assert_eq!(
+        calibration.try_write(Calibration::Duty, f32::NAN),
+        Err(InvalidValue::OutOfRange(ValueOutOfRange {
+            field: "Duty",
+            value: 0,
+            min: 0,
+            max: 0xff
+        }))
+    );
+    assert_eq!(calibration.get(), 0);
+    assert_eq!(calibration.try_write(Calibration::Duty, 15.9375), Ok(()));
+    assert_eq!(calibration.get(), 0xff_0000);
+}
+
+#[test]
+fn accesses_raw_bits() {
+    let mut calibration = Calibration::new();
+
+    calibration.write(Calibration::Gain, 0x8000);
+    assert_eq!(calibration.read(Calibration::Gain).value(), 0x8000);
+    assert_eq!(calibration.read(Calibration::Gain).get(), Q::MIN);
+
+    calibration.write(Calibration::Duty, UQ::new(0x18).unwrap());
+    assert_eq!(calibration.read(Calibration::Duty).get().to_f64(), 1.5);
+}
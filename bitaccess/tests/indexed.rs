@@ -0,0 +1,79 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+// Don't do this at home
+static mut CHANNELS: [u32; 4] = [0; 4];
+static mut BREAKPOINTS: [u64; 16] = [0; 16];
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    index: usize,
+    count = 4,
+    read_via = { unsafe { value = crate::CHANNELS[index] } },
+    write_via = { unsafe { crate::CHANNELS[index] = value } }
+)]
+pub enum DmaControl {
+    #[bit(0)]
+    Enable,
+    #[bits(4..8)]
+    Priority,
+}
+
+fn read_breakpoint(index: usize) -> u64 {
+    unsafe { BREAKPOINTS[index] }
+}
+
+fn write_breakpoint(index: usize, value: u64) {
+    unsafe { BREAKPOINTS[index] = value }
+}
+
+#[bitaccess(
+    base_type = u64,
+    kind = read_write,
+    index: usize,
+    count = 16,
+    read_via = crate::read_breakpoint,
+    write_via = crate::write_breakpoint
+)]
+pub enum BreakpointValue {
+    #[bits(2..49)]
+    Address,
+}
+
+#[test]
+fn accesses_selected_register() {
+    unsafe { CHANNELS = [0; 4] };
+    DmaControl::at(2).write(DmaControl::Priority, 0xa);
+    DmaControl::at(3).write(DmaControl::Enable, 1);
+
+    assert_eq!(unsafe { CHANNELS }, [0, 0, 0xa0, 1]);
+    assert_eq!(DmaControl::at(2).read(DmaControl::Priority).value(), 0xa);
+    assert_eq!(DmaControl::at(2).fetch().get(), 0xa0);
+    assert_eq!(DmaControl::at(3).index(), 3);
+}
+
+#[test]
+fn passes_index_to_functions() {
+    BreakpointValue::at(15).write(BreakpointValue::Address, 0x1000);
+
+    assert_eq!(unsafe { BREAKPOINTS[15] }, 0x1000 << 2);
+    assert_eq!(
+        BreakpointValue::at(15)
+            .read(BreakpointValue::Address)
+            .value(),
+        0x1000
+    );
+    assert_eq!(BreakpointValue::COUNT, 16);
+}
+
+#[test]
+fn checks_bounds() {
+    assert!(DmaControl::try_at(3).is_some());
+    assert!(DmaControl::try_at(4).is_none());
+}
+
+#[test]
+#[should_panic(expected = "register index out of bounds")]
+fn panics_on_index_out_of_bounds() {
+    let _ = DmaControl::at(4);
+}
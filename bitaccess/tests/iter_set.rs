@@ -0,0 +1,67 @@
+use bitaccess::{bitaccess, WriteBits};
+
+// Don't do this at home
+static mut IRQ_PENDING: u32 = 0;
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_only,
+    read_via = { value = unsafe { crate::IRQ_PENDING } },
+    iter_set = true
+)]
+pub enum IrqStatus {
+    #[bit(0)]
+    Uart,
+    #[bits(1..4)]
+    Reserved,
+    #[bit(4)]
+    Timer,
+    #[bit(7)]
+    #[priority(0)]
+    Watchdog,
+    #[bit(9)]
+    #[priority(1)]
+    Dma,
+    #[bit(12)]
+    Gpio,
+}
+
+#[bitaccess(base_type = u8, kind = read_write, iter_set = true)]
+pub enum Pins {
+    #[bit(0)]
+    Led,
+    #[bit(1)]
+    Button,
+    #[bits(4..8)]
+    Mode,
+}
+
+#[test]
+fn iterates_over_pending_flags() {
+    unsafe { IRQ_PENDING = 0x109f }
+    let pending: Vec<_> = IrqStatus::fetch().iter_set().collect();
+
+    assert_eq!(
+        pending,
+        [
+            IrqStatusFlag::Watchdog,
+            IrqStatusFlag::Uart,
+            IrqStatusFlag::Timer,
+            IrqStatusFlag::Gpio,
+        ]
+    );
+    assert_eq!(IrqStatusFlag::Dma.mask(), 0x200);
+    assert_eq!(
+        IrqStatusFlag::iter_set(0x200).collect::<Vec<_>>(),
+        [IrqStatusFlag::Dma]
+    );
+}
+
+#[test]
+fn iterates_over_inline_register() {
+    let mut pins = Pins::new();
+    pins.write(Pins::Button, 1);
+    pins.write(Pins::Mode, 0xf);
+
+    assert_eq!(pins.iter_set().collect::<Vec<_>>(), [PinsFlag::Button]);
+}
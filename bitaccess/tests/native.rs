@@ -0,0 +1,58 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+#[bitaccess(base_type = u64, kind = read_write)]
+pub enum Uart {
+    #[bit(3)]
+    #[ty(bool)]
+    Enabled,
+    #[bits(8..16)]
+    #[ty]
+    Data,
+    #[bits(16..28)]
+    #[ty]
+    Divisor,
+    #[bits(32..40)]
+    #[ty(char)]
+    Terminator,
+    #[bit(40)]
+    #[ty]
+    Parity,
+}
+
+#[test]
+fn reads_and_writes_native_types() {
+    let mut uart = Uart::new();
+    uart.write(Uart::Enabled, true);
+    uart.write(Uart::Data, 0xa5u8);
+    uart.write(Uart::Divisor, 0x123u16);
+    uart.write(Uart::Terminator, '\n');
+
+    assert_eq!(uart.get(), 0x0a_0123_a508);
+    assert!(uart.read(Uart::Enabled).get());
+    assert!(!uart.read(Uart::Parity).get());
+    assert_eq!(uart.read(Uart::Data).get(), 0xa5u8);
+    assert_eq!(uart.read(Uart::Divisor).get(), 0x123u16);
+    assert_eq!(uart.read(Uart::Terminator).get(), '\n');
+    assert_eq!(uart.read(Uart::Data).value(), 0xa5u64);
+
+    uart.write(Uart::Enabled, false);
+    assert!(!uart.read(Uart::Enabled).get());
+}
+
+#[test]
+#[should_panic(expected = "ValueTooWide { field: \"Terminator\", value: 261, max: 255 }")]
+fn rejects_characters_outside_latin1() {
+    Uart::new().write(Uart::Terminator, 'ą');
+}
+
+#[test]
+fn try_write_rejects_characters_outside_latin1() {
+    let mut uart = Uart::new();
+
+    assert!(matches!(
+        uart.try_write(Uart::Terminator, 'ą'),
+        Err(bitaccess::InvalidValue::TooWide(_))
+    ));
+    assert_eq!(uart.try_write(Uart::Terminator, 'é'), Ok(()));
+    assert_eq!(uart.read(Uart::Terminator).get(), 'é');
+}
@@ -0,0 +1,65 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+// Don't do this at home
+static mut FENCED_TEST: u32 = 0;
+static mut COMPILER_TEST: u32 = 0;
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    ordering = fenced,
+    read_via = { unsafe { value = core::ptr::read_volatile(core::ptr::addr_of!(crate::FENCED_TEST)) } },
+    write_via = { unsafe { core::ptr::write_volatile(core::ptr::addr_of_mut!(crate::FENCED_TEST), value) } }
+)]
+pub enum Doorbell {
+    #[bits(0..8)]
+    Channel,
+    #[bit(31)]
+    Ring,
+}
+
+#[bitaccess(
+    base_type = u32,
+    kind = write_only,
+    ordering = compiler,
+    write_via = |v: u32| unsafe { crate::COMPILER_TEST = v }
+)]
+pub enum Kick {
+    #[bits(0..16)]
+    Value,
+}
+
+#[test]
+fn fenced_register_reads_and_writes() {
+    Doorbell.write(Doorbell::Channel, 0x12);
+    Doorbell.write(Doorbell::Ring, 1);
+    assert_eq!(unsafe { FENCED_TEST }, 0x8000_0012);
+    assert_eq!(Doorbell.read(Doorbell::Channel).value(), 0x12);
+    assert_eq!(Doorbell.read(Doorbell::Ring).value(), 1);
+}
+
+#[test]
+fn compiler_ordered_register_writes() {
+    Kick.write(Kick::Value, 0x55);
+    assert_eq!(unsafe { COMPILER_TEST }, 0x55);
+}
+
+static mut PER_CALL_TEST: u32 = 0;
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    read_via = { unsafe { value = core::ptr::read_volatile(core::ptr::addr_of!(crate::PER_CALL_TEST)) } },
+    write_via = { unsafe { core::ptr::write_volatile(core::ptr::addr_of_mut!(crate::PER_CALL_TEST), value) } }
+)]
+pub enum Tail {
+    #[bits(0..16)]
+    Index,
+}
+
+#[test]
+fn fences_single_accesses() {
+    Tail.write_fenced(Tail::Index, 0x40);
+    assert_eq!(unsafe { PER_CALL_TEST }, 0x40);
+    assert_eq!(Tail.read_fenced(Tail::Index).value(), 0x40);
+}
@@ -0,0 +1,72 @@
+use bitaccess::{bitaccess, InvalidValue, ReadBits, ValueOutOfRange, WriteBits};
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum Pll {
+    #[bits(0..4, range = 1..=15)]
+    Divider,
+    #[bits(4..10, range = 8..50)]
+    Multiplier,
+    #[bits(10..12)]
+    Source,
+}
+
+#[test]
+fn accepts_values_in_range() {
+    let mut pll = Pll::new();
+
+    pll.write(Pll::Divider, 1);
+    pll.write(Pll::Multiplier, 49);
+    assert_eq!(pll.read(Pll::Divider).value(), 1);
+    assert_eq!(pll.read(Pll::Multiplier).value(), 49);
+}
+
+#[test]
+#[should_panic(expected = "value out of range of field Divider")]
+fn write_panics_out_of_range() {
+    let mut pll = Pll::new();
+
+    pll.write(Pll::Divider, 0);
+}
+
+#[test]
+fn try_write_rejects_out_of_range() {
+    let mut pll = Pll::new();
+
+    assert_eq!(
+        pll.try_write(Pll::Multiplier, 50),
+        Err(InvalidValue::OutOfRange(ValueOutOfRange {
+            field: "Multiplier",
+            value: 50,
+            min: 8,
+            max: 49
+        }))
+    );
+    assert!(matches!(
+        pll.try_write(Pll::Divider, 0x10),
+        Err(InvalidValue::TooWide(_))
+    ));
+    assert_eq!(pll.try_write(Pll::Source, 3), Ok(()));
+    assert_eq!(pll.get(), 0xc00);
+}
+
+#[test]
+fn lists_ranges_in_fields() {
+    let ranges: Vec<_> = Pll::FIELDS.iter().map(|field| field.range).collect();
+
+    assert_eq!(ranges, [Some((1, 15)), Some((8, 49)), None]);
+}
+
+#[test]
+fn validates_register_values() {
+    assert_eq!(Pll::try_from_value(0x8a1).map(|pll| pll.get()), Ok(0x8a1));
+    assert_eq!(
+        Pll::try_from_value(0x8a0).map(|pll| pll.get()),
+        Err(InvalidValue::OutOfRange(ValueOutOfRange {
+            field: "Divider",
+            value: 0,
+            min: 1,
+            max: 15
+        }))
+    );
+    assert!(Pll::try_from_value(0x41).is_err());
+}
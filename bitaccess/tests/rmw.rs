@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+static LOCKED_REGISTER: AtomicU64 = AtomicU64::new(0);
+static LOCKS_TAKEN: AtomicUsize = AtomicUsize::new(0);
+
+fn with_lock<R>(f: impl FnOnce() -> R) -> R {
+    LOCKS_TAKEN.fetch_add(1, Ordering::SeqCst);
+    f()
+}
+
+#[bitaccess(
+    base_type = u64,
+    kind = read_write,
+    rmw = custom(crate::with_lock),
+    read_via = || crate::LOCKED_REGISTER.load(std::sync::atomic::Ordering::Relaxed),
+    write_via = |v| crate::LOCKED_REGISTER.store(v, std::sync::atomic::Ordering::Relaxed)
+)]
+pub enum Locked {
+    #[bits(0..4)]
+    Low,
+    #[bits(4..8)]
+    High,
+}
+
+#[test]
+fn custom_hook_guards_read_modify_write() {
+    let before = LOCKS_TAKEN.load(Ordering::SeqCst);
+    Locked.write(Locked::Low, 0x3);
+    Locked.modify(|r| r.write_to_cache(Locked::High, 0x5));
+
+    assert_eq!(LOCKS_TAKEN.load(Ordering::SeqCst), before + 2);
+    assert_eq!(Locked.read(Locked::Low).value(), 0x3);
+    assert_eq!(LOCKED_REGISTER.load(Ordering::SeqCst), 0x53);
+}
+
+#[cfg(feature = "critical-section")]
+mod with_critical_section {
+    use std::{
+        sync::atomic::{AtomicU64, Ordering},
+        thread,
+    };
+
+    use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+    static SHARED_REGISTER: AtomicU64 = AtomicU64::new(0);
+
+    #[bitaccess(
+        base_type = u64,
+        kind = read_write,
+        rmw = critical_section,
+        read_via = || crate::with_critical_section::SHARED_REGISTER.load(std::sync::atomic::Ordering::Relaxed),
+        write_via = |v| crate::with_critical_section::SHARED_REGISTER.store(v, std::sync::atomic::Ordering::Relaxed)
+    )]
+    pub enum Shared {
+        #[bits(0..16)]
+        First,
+        #[bits(16..32)]
+        Second,
+    }
+
+    #[test]
+    fn critical_section_keeps_concurrent_writes() {
+        let handles: Vec<_> = (0..2)
+            .map(|n| {
+                thread::spawn(move || {
+                    for i in 0..10_000u64 {
+                        if n == 0 {
+                            Shared.write(Shared::First, i);
+                        } else {
+                            Shared.modify(|r| {
+                                let second = r.read(Shared::Second).value();
+                                r.write_to_cache(Shared::Second, second + 1);
+                            });
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(Shared.read(Shared::First).value(), 9_999);
+        assert_eq!(Shared.read(Shared::Second).value(), 10_000);
+        assert_eq!(
+            SHARED_REGISTER.load(Ordering::SeqCst),
+            (10_000 << 16) | 9_999
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use bitaccess::{bitaccess, InvalidValue, ReadBits, ValueMisaligned, WriteBits};
+
+#[bitaccess(base_type = u64, kind = read_write)]
+pub enum PageTableEntry {
+    #[bit(0)]
+    Present,
+    #[bits(12..48, shift = 12)]
+    Frame,
+}
+
+#[bitaccess(base_type = u64, kind = read_write)]
+pub enum Descriptor {
+    #[bits(4..32, shift = 4)]
+    #[ty]
+    Next,
+    #[bits(32..64, shift = 2)]
+    #[ty(*const u32)]
+    Buffer,
+}
+
+#[test]
+fn reads_full_address() {
+    let mut entry = PageTableEntry::new();
+
+    entry.write(PageTableEntry::Present, 1);
+    entry.write(PageTableEntry::Frame, 0x8020_3000);
+    assert_eq!(entry.get(), 0x8020_3001);
+    assert_eq!(entry.read(PageTableEntry::Frame).get(), 0x8020_3000);
+}
+
+#[test]
+#[should_panic(expected = "misaligned value written to field Frame")]
+fn write_panics_when_misaligned() {
+    let mut entry = PageTableEntry::new();
+
+    entry.write(PageTableEntry::Frame, 0x8020_3004);
+}
+
+#[test]
+fn try_write_rejects_misaligned() {
+    let mut entry = PageTableEntry::new();
+
+    assert_eq!(
+        entry.try_write(PageTableEntry::Frame, 0x1001),
+        Err(InvalidValue::Misaligned(ValueMisaligned {
+            field: "Frame",
+            value: 0x1001,
+            align: 0x1000
+        }))
+    );
+    assert!(matches!(
+        entry.try_write(PageTableEntry::Frame, 1 << 48),
+        Err(InvalidValue::TooWide(_))
+    ));
+    assert_eq!(entry.get(), 0);
+}
+
+#[test]
+fn accesses_usize_and_pointers() {
+    let mut descriptor = Descriptor::new();
+    let buffer = 0x2000_0100 as *const u32;
+
+    descriptor.write(Descriptor::Next, 0x1000_0040usize);
+    descriptor.write(Descriptor::Buffer, buffer);
+    assert_eq!(descriptor.get(), 0x0800_0040_1000_0040);
+
+    let next: usize = descriptor.read(Descriptor::Next).get();
+    assert_eq!(next, 0x1000_0040);
+    assert_eq!(descriptor.read(Descriptor::Buffer).get(), buffer);
+}
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum DmaAddress {
+    #[bits(2..32, shift = 2)]
+    #[ty]
+    Address,
+}
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum DmaBuffer {
+    #[bits(2..32, shift = 2)]
+    #[ty(*const u8)]
+    Buffer,
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn rejects_addresses_wider_than_base_type() {
+    let mut dma = DmaAddress::new();
+
+    assert!(matches!(
+        dma.try_write(DmaAddress::Address, 0x1_0000_0000usize),
+        Err(InvalidValue::TooWide(_))
+    ));
+    assert_eq!(dma.get(), 0);
+
+    let mut buffer = DmaBuffer::new();
+    assert!(matches!(
+        buffer.try_write(DmaBuffer::Buffer, 0x1_2000_0000 as *const u8),
+        Err(InvalidValue::TooWide(_))
+    ));
+    assert_eq!(buffer.get(), 0);
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+#[should_panic(expected = "ValueTooWide")]
+fn write_panics_on_addresses_wider_than_base_type() {
+    DmaAddress::new().write(DmaAddress::Address, 0x1_0000_0000usize);
+}
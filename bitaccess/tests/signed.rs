@@ -0,0 +1,80 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+#[bitaccess(base_type = u16, kind = read_write)]
+pub enum Calibration {
+    #[bits(0..4)]
+    Gain,
+    #[bits(4..12, signed)]
+    TemperatureOffset,
+    #[bits(12..16, signed = i32)]
+    Trim,
+}
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum JType {
+    #[bits(0..7)]
+    Opcode,
+    // imm[20|19:12|11|10:1], from the most significant bit of the immediate
+    #[bits(31, 19..12, 20, 30..21, signed)]
+    Imm,
+}
+
+#[test]
+fn sign_extends_reads() {
+    let mut calibration = Calibration::new();
+    calibration.write(Calibration::Gain, 0xf);
+    calibration.write(Calibration::TemperatureOffset, -3i8);
+    calibration.write(Calibration::Trim, 7);
+
+    assert_eq!(calibration.get(), 0x7fdf);
+    assert_eq!(calibration.read(Calibration::TemperatureOffset).get(), -3i8);
+    assert_eq!(calibration.read(Calibration::Trim).get(), 7i32);
+    assert_eq!(calibration.read(Calibration::Gain).value(), 0xf);
+
+    calibration.write(Calibration::Trim, -8);
+    assert_eq!(calibration.read(Calibration::Trim).get(), -8);
+    assert_eq!(calibration.get(), 0x8fdf);
+}
+
+#[test]
+fn sign_extends_split_fields() {
+    // jal x0, -16
+    let mut instruction = JType::new();
+    instruction.write(JType::Opcode, 0b110_1111);
+    instruction.write(JType::Imm, -16 >> 1);
+
+    assert_eq!(instruction.get(), 0xff1f_f06f);
+    assert_eq!(instruction.read(JType::Imm).get(), -8);
+}
+
+#[test]
+#[should_panic(expected = "value out of range of signed field Trim")]
+fn checks_range_of_written_values() {
+    Calibration::new().write(Calibration::Trim, -9);
+}
+
+#[test]
+#[should_panic(expected = "value out of range of signed field Trim")]
+fn checks_range_of_positive_values() {
+    let mut calibration = Calibration::new();
+    calibration.write(Calibration::TemperatureOffset, i8::MAX);
+    calibration.write(Calibration::Trim, 8);
+}
+
+#[test]
+#[should_panic(expected = "value out of range of signed field Trim")]
+fn checks_range_before_narrowing_to_base_type() {
+    // 65539 is 3 once truncated to u16
+    Calibration::new().write(Calibration::Trim, 65539);
+}
+
+#[test]
+fn rejects_values_wider_than_base_type() {
+    let mut calibration = Calibration::new();
+
+    assert!(matches!(
+        calibration.try_write(Calibration::Trim, 65539),
+        Err(bitaccess::InvalidValue::TooWide(_))
+    ));
+    assert_eq!(calibration.get(), 0);
+}
@@ -0,0 +1,46 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum BType {
+    #[bits(0..7)]
+    Opcode,
+    #[bits(12..15)]
+    Funct3,
+    // imm[12|11|10:5|4:1]
+    #[bits(31, 7, 30..25, 11..8)]
+    Imm,
+}
+
+#[bitaccess(base_type = u16, kind = read_write)]
+pub enum Divider {
+    #[bits(0..4, 12..=15, order = lsb_first)]
+    Value,
+    #[bits(4..12)]
+    Other,
+}
+
+#[test]
+fn assembles_and_scatters_pieces() {
+    // beq x0, x0, -4
+    let mut instruction = BType::new();
+    instruction.write(BType::Opcode, 0b110_0011);
+    instruction.write(BType::Imm, (-4i32 >> 1) as u32 & 0xfff);
+
+    assert_eq!(instruction.get(), 0xfe00_0ee3);
+    assert_eq!(instruction.read(BType::Imm).value(), 0xffe);
+    assert_eq!(instruction.read(BType::Funct3).value(), 0);
+    assert_eq!(BType::Imm.mask(), 0xfe00_0f80);
+
+    instruction.write(BType::Imm, 0x801);
+    assert_eq!(instruction.get(), 0x8000_0163);
+}
+
+#[test]
+fn concatenates_in_given_order() {
+    let mut divider = Divider::new();
+    divider.write(Divider::Value, 0xa5);
+
+    assert_eq!(divider.get(), 0xa005);
+    assert_eq!(divider.read(Divider::Value).value(), 0xa5);
+    assert_eq!(divider.read(Divider::Other).value(), 0);
+}
@@ -0,0 +1,41 @@
+use bitaccess::{bitaccess, InvalidValue, ReadBits, ValueTooWide};
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum Clock {
+    #[bits(0..4)]
+    Divider,
+    #[bits(4..8, signed)]
+    Trim,
+    #[bits(8..10, count = 4)]
+    Source,
+}
+
+#[test]
+fn rejects_too_wide_values() {
+    let mut clock = Clock::new();
+
+    assert_eq!(clock.try_write(Clock::Divider, 0xf), Ok(()));
+    assert_eq!(
+        clock.try_write(Clock::Divider, 0x10),
+        Err(InvalidValue::TooWide(ValueTooWide {
+            field: "Divider",
+            value: 0x10,
+            max: 0xf
+        }))
+    );
+    assert_eq!(clock.read(Clock::Divider).value(), 0xf);
+
+    assert_eq!(clock.try_write(Clock::Trim, -8), Ok(()));
+    assert_eq!(
+        clock.try_write(Clock::Trim, 8),
+        Err(InvalidValue::TooWide(ValueTooWide {
+            field: "Trim",
+            value: 8,
+            max: 7
+        }))
+    );
+
+    assert_eq!(clock.try_write(Clock::Source.at(3), 3), Ok(()));
+    assert!(clock.try_write(Clock::Source.at(2), 4).is_err());
+    assert_eq!(clock.get(), 0xc08f);
+}
@@ -0,0 +1,40 @@
+use bitaccess::{bitaccess, ReadBits, UInt, WriteBits};
+
+#[bitaccess(base_type = u32, kind = read_write)]
+pub enum Timer {
+    #[bits(0..8)]
+    Prescaler,
+    #[bits(8..20)]
+    Reload,
+}
+
+#[test]
+fn reads_and_writes_exact_width_values() {
+    let mut timer = Timer::new();
+    timer.write(Timer::Prescaler, UInt::<8>::new_const::<200>());
+    timer.write(Timer::Reload, UInt::new(0xabc).unwrap());
+
+    assert_eq!(timer.get(), 0xa_bcc8);
+    assert_eq!(
+        timer.read(Timer::Prescaler).get(),
+        UInt::<8>::new_const::<200>()
+    );
+    assert_eq!(timer.read(Timer::Reload).get().get(), 0xabc);
+
+    // Raw values are still accepted
+    timer.write(Timer::Prescaler, 0x10);
+    assert_eq!(timer.read(Timer::Prescaler).value(), 0x10);
+}
+
+#[test]
+fn rejects_too_wide_values() {
+    assert_eq!(UInt::<8>::new(300), None);
+    assert_eq!(UInt::<8>::MAX.get(), 0xff);
+    assert_eq!(UInt::<128>::MAX.get(), u128::MAX);
+}
+
+#[test]
+#[should_panic(expected = "ValueTooWide { field: \"Prescaler\", value: 256, max: 255 }")]
+fn rejects_too_wide_raw_values() {
+    Timer::new().write(Timer::Prescaler, 0x100);
+}
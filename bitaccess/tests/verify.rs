@@ -0,0 +1,147 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+// Don't do this at home
+static mut LINK_REGISTER: u32 = 0;
+// Bits the register silently ignores on write
+static mut LINK_STUCK: u32 = 0;
+
+fn link_read() -> u32 {
+    unsafe { LINK_REGISTER }
+}
+
+fn link_write(value: u32) {
+    unsafe { LINK_REGISTER = (value & !LINK_STUCK) | (LINK_REGISTER & LINK_STUCK) }
+}
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    read_via = crate::link_read,
+    write_via = crate::link_write
+)]
+pub enum Link {
+    #[bits(0..8)]
+    Speed,
+    #[bits(8..12)]
+    Mode,
+    #[bit(31)]
+    #[volatile]
+    Busy,
+}
+
+// Don't do this at home
+static mut CHECKED_REGISTER: u16 = 0;
+static mut CHECKED_STUCK: u16 = 0;
+
+#[bitaccess(
+    base_type = u16,
+    kind = read_write,
+    verify_writes = true,
+    read_via = { value = unsafe { crate::CHECKED_REGISTER } },
+    write_via = { unsafe { crate::CHECKED_REGISTER = value & !crate::CHECKED_STUCK } }
+)]
+pub enum Checked {
+    #[bits(0..4)]
+    Low,
+    #[bits(4..8)]
+    High,
+}
+
+// Don't do this at home
+static mut IGNORING_REGISTER: u8 = 0;
+
+#[bitaccess(
+    base_type = u8,
+    kind = read_write,
+    verify_writes = true,
+    read_via = { value = unsafe { crate::IGNORING_REGISTER } },
+    write_via = { unsafe { crate::IGNORING_REGISTER = value & 0xf0 } }
+)]
+pub enum Ignoring {
+    #[bit(0)]
+    Enable,
+}
+
+#[test]
+fn lists_fields() {
+    let names: Vec<_> = Link::FIELDS.iter().map(|field| field.name).collect();
+
+    assert_eq!(names, ["Speed", "Mode", "Busy"]);
+    assert_eq!(Link::FIELDS[1].mask, 0xf00);
+}
+
+#[test]
+fn verified_writes() {
+    let mut link = Link::new();
+
+    assert!(link.write_verified(Link::Speed, 0x42).is_ok());
+    assert_eq!(link.read(Link::Speed).value(), 0x42);
+
+    unsafe { LINK_STUCK = 0x300 }
+    let mismatch = link.write_verified(Link::Mode, 0xf).unwrap_err();
+    assert_eq!(mismatch.expected(), 0xf00);
+    assert_eq!(mismatch.actual(), 0xc00);
+    assert_eq!(
+        mismatch
+            .fields()
+            .map(|field| field.name)
+            .collect::<Vec<_>>(),
+        ["Mode"]
+    );
+    assert_eq!(
+        format!("{:?}", mismatch),
+        "WriteMismatch { expected: 3840, actual: 3072, fields: [\"Mode\"] }"
+    );
+
+    // Volatile fields are skipped
+    unsafe { LINK_STUCK = 0x8000_0000 }
+    assert!(link.write_verified(Link::Busy, 1).is_ok());
+    assert!(link.verify_write(0x8000_0c42, !0).is_ok());
+}
+
+#[test]
+fn verify_writes_option() {
+    unsafe {
+        CHECKED_REGISTER = 0;
+        CHECKED_STUCK = 0;
+    }
+    let mut checked = Checked::new();
+
+    checked.write(Checked::Low, 0x5);
+    checked.modify(|r| r.write_to_cache(Checked::High, 0xa));
+    assert_eq!(checked.get(), 0xa5);
+
+    unsafe { CHECKED_STUCK = 0x10 }
+    let mismatch = checked.write_verified(Checked::High, 0x1).unwrap_err();
+    assert_eq!(
+        mismatch
+            .fields()
+            .map(|field| field.name)
+            .collect::<Vec<_>>(),
+        ["High"]
+    );
+}
+
+#[test]
+#[should_panic(expected = "register doesn't hold written value")]
+fn verify_writes_panics_on_mismatch() {
+    let mut ignoring = Ignoring::new();
+
+    ignoring.write(Ignoring::Enable, 1);
+}
+
+#[test]
+#[should_panic(expected = "register doesn't hold written value")]
+fn verify_writes_checks_set() {
+    let mut ignoring = Ignoring::new();
+
+    ignoring.set(0x01);
+}
+
+#[test]
+#[should_panic(expected = "register doesn't hold written value")]
+fn verify_writes_checks_modify() {
+    let mut ignoring = Ignoring::new();
+
+    ignoring.modify(|r| r.write_to_cache(Ignoring::Enable, 1));
+}
@@ -0,0 +1,80 @@
+use bitaccess::{bitaccess, ReadBits, WriteBits};
+
+// Don't do this at home
+static mut BLOCK_TEST: u64 = 0;
+static mut PATH_TEST: u64 = 0;
+static mut CLOSURE_TEST: u32 = 0;
+
+#[bitaccess(
+    base_type = u64,
+    kind = read_write,
+    read_via = { unsafe { value = crate::BLOCK_TEST } },
+    write_via = { unsafe { crate::BLOCK_TEST = value } }
+)]
+pub enum BlockVia {
+    #[bits(0..4)]
+    Low,
+    #[bits(4..8)]
+    High,
+}
+
+fn read_path_test() -> u64 {
+    unsafe { PATH_TEST }
+}
+
+fn write_path_test(value: u64) {
+    unsafe { PATH_TEST = value }
+}
+
+#[bitaccess(
+    base_type = u64,
+    kind = read_write,
+    read_via = crate::read_path_test,
+    write_via = crate::write_path_test
+)]
+pub enum PathVia {
+    #[bits(0..4)]
+    Low,
+    #[bits(4..8)]
+    High,
+}
+
+#[bitaccess(
+    base_type = u32,
+    kind = read_write,
+    read_via = || -> u32 { unsafe { crate::CLOSURE_TEST } },
+    write_via = |v: u32| unsafe { crate::CLOSURE_TEST = v }
+)]
+pub enum ClosureVia {
+    #[bits(0..4)]
+    Low,
+    #[bits(4..8)]
+    High,
+}
+
+#[test]
+fn block_via_reads_and_writes() {
+    unsafe { BLOCK_TEST = 0x21 };
+    assert_eq!(BlockVia.read(BlockVia::Low).value(), 1);
+    BlockVia.write(BlockVia::High, 0x7);
+    assert_eq!(unsafe { BLOCK_TEST }, 0x71);
+}
+
+#[test]
+fn path_via_reads_and_writes() {
+    unsafe { PATH_TEST = 0x21 };
+    assert_eq!(PathVia.read(PathVia::High).value(), 2);
+    PathVia.write(PathVia::Low, 0x5);
+    assert_eq!(unsafe { PATH_TEST }, 0x25);
+    assert_eq!(PathVia::fetch().get(), 0x25);
+}
+
+#[test]
+fn closure_via_reads_and_writes() {
+    unsafe { CLOSURE_TEST = 0x43 };
+    assert_eq!(ClosureVia.read(ClosureVia::Low).value(), 3);
+    ClosureVia.write(ClosureVia::High, 0x1);
+    assert_eq!(unsafe { CLOSURE_TEST }, 0x13);
+    ClosureVia.set(0xff);
+    assert_eq!(unsafe { CLOSURE_TEST }, 0xff);
+}
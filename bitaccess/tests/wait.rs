@@ -0,0 +1,98 @@
+use std::{
+    future::Future,
+    pin::pin,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use bitaccess::{bitaccess, Timeout};
+
+// PLL locks after 3 reads
+static PLL_READS: AtomicU32 = AtomicU32::new(0);
+
+fn pll_read() -> u32 {
+    let reads = PLL_READS.fetch_add(1, Ordering::Relaxed) + 1;
+    (reads << 8) | u32::from(reads >= 3)
+}
+
+#[bitaccess(base_type = u32, kind = read_only, read_via = crate::pll_read)]
+pub enum Pll {
+    #[bit(0)]
+    #[variants(No => 0, Yes => 1)]
+    Locked,
+    #[bits(8..16)]
+    Reads,
+}
+
+// FIFO drains one entry per read
+static FIFO_READS: AtomicU32 = AtomicU32::new(0);
+
+fn fifo_read() -> u8 {
+    4u8.saturating_sub(FIFO_READS.fetch_add(1, Ordering::Relaxed) as u8)
+}
+
+#[bitaccess(base_type = u8, kind = read_only, read_via = crate::fifo_read)]
+pub enum Fifo {
+    #[bits(0..4)]
+    Level,
+}
+
+// DMA finishes after 2 reads
+static DMA_READS: AtomicU32 = AtomicU32::new(0);
+
+fn dma_read() -> u16 {
+    u16::from(DMA_READS.fetch_add(1, Ordering::Relaxed) < 2)
+}
+
+#[bitaccess(base_type = u16, kind = read_only, read_via = crate::dma_read)]
+pub enum Dma {
+    #[bit(0)]
+    Busy,
+}
+
+/// Runs `future` to completion, counting how many times it yielded.
+fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    let mut polls = 0;
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return (output, polls);
+        }
+        polls += 1;
+    }
+}
+
+#[test]
+fn waits_for_fields() {
+    PLL_READS.store(0, Ordering::Relaxed);
+
+    assert_eq!(Pll.wait_until(Pll::Locked, Locked::Yes, 10), Ok(()));
+    assert_eq!(PLL_READS.load(Ordering::Relaxed), 3);
+
+    PLL_READS.store(0, Ordering::Relaxed);
+    assert_eq!(
+        Pll.wait_until(Pll::Locked, Locked::Yes, 2),
+        Err(Timeout { last: 0x200 })
+    );
+
+    FIFO_READS.store(0, Ordering::Relaxed);
+    assert_eq!(Fifo.wait_while(Fifo::Level, 4, 1), Err(Timeout { last: 4 }));
+    assert_eq!(Fifo.wait_while(Fifo::Level, 2, 10), Ok(()));
+    assert_eq!(Fifo.get(), 2);
+}
+
+#[test]
+fn waits_asynchronously() {
+    DMA_READS.store(0, Ordering::Relaxed);
+
+    assert_eq!(
+        block_on(Dma.wait_until_async(Dma::Busy, 0, 1)),
+        (Err(Timeout { last: 1 }), 0)
+    );
+    assert_eq!(
+        block_on(Dma.wait_while_async(Dma::Busy, 1, 10)),
+        (Ok(()), 1)
+    );
+    assert_eq!(DMA_READS.load(Ordering::Relaxed), 3);
+}
@@ -0,0 +1,453 @@
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_quote, LitInt, LitStr, Type};
+
+use crate::top_level_macro_arguments::Via;
+
+/// Built-in backends for registers accessed with dedicated CPU instructions.
+pub enum ArchRegister {
+    /// AArch64 system register, accessed with `mrs`/`msr`.
+    SysReg(LitStr),
+    /// RISC-V control and status register, accessed with `csrr`/`csrw`/`csrc`/`csrs`.
+    Csr(LitInt),
+    /// x86 model specific register, accessed with `rdmsr`/`wrmsr`.
+    Msr(LitInt),
+}
+
+impl ArchRegister {
+    pub fn read_via(&self, base_type: &Type, count: Option<u64>, stride: u32) -> syn::Result<Via> {
+        let expr = match self {
+            Self::SysReg(_) => {
+                let read = self.per_register(count, |n| {
+                    let template = self.template(format!("mrs {{}}, {}", self.sysreg_name(n)?));
+                    Ok(quote! {
+                        unsafe {
+                            core::arch::asm!(#template, out(reg) raw, options(nomem, nostack));
+                        }
+                    })
+                })?;
+                parse_quote! {
+                    {
+                        let raw: u64;
+                        #read
+                        value = raw as #base_type;
+                    }
+                }
+            }
+            Self::Csr(_) => {
+                let read = self.per_register(count, |n| {
+                    let template = self.template(format!("csrr {{}}, {:#x}", self.csr(n, stride)?));
+                    Ok(quote! {
+                        unsafe {
+                            core::arch::asm!(#template, out(reg) raw, options(nomem, nostack));
+                        }
+                    })
+                })?;
+                parse_quote! {
+                    {
+                        let raw: usize;
+                        #read
+                        value = raw as #base_type;
+                    }
+                }
+            }
+            Self::Msr(_) => {
+                let msr = self.msr(count, stride)?;
+                parse_quote! {
+                    {
+                        let lo: u32;
+                        let hi: u32;
+                        unsafe {
+                            core::arch::asm!(
+                                "rdmsr",
+                                in("ecx") #msr,
+                                out("eax") lo,
+                                out("edx") hi,
+                                options(nomem, nostack, preserves_flags)
+                            );
+                        }
+                        value = (((hi as u64) << 32) | lo as u64) as #base_type;
+                    }
+                }
+            }
+        };
+
+        Ok(Via::Statements(expr))
+    }
+
+    pub fn write_via(&self, count: Option<u64>, stride: u32) -> syn::Result<Via> {
+        let expr = match self {
+            Self::SysReg(_) => {
+                let write = self.per_register(count, |n| {
+                    let template = self.template(format!("msr {}, {{}}", self.sysreg_name(n)?));
+                    Ok(quote! {
+                        unsafe {
+                            core::arch::asm!(#template, in(reg) value as u64, options(nostack));
+                        }
+                    })
+                })?;
+                parse_quote! { #write }
+            }
+            Self::Csr(_) => {
+                let write = self.per_register(count, |n| {
+                    let template = self.template(format!("csrw {:#x}, {{}}", self.csr(n, stride)?));
+                    Ok(quote! {
+                        unsafe {
+                            core::arch::asm!(#template, in(reg) value as usize, options(nostack));
+                        }
+                    })
+                })?;
+                parse_quote! { #write }
+            }
+            Self::Msr(_) => {
+                let msr = self.msr(count, stride)?;
+                parse_quote! {
+                    unsafe {
+                        core::arch::asm!(
+                            "wrmsr",
+                            in("ecx") #msr,
+                            in("eax") value as u64 as u32,
+                            in("edx") ((value as u64) >> 32) as u32,
+                            options(nostack, preserves_flags)
+                        );
+                    }
+                }
+            }
+        };
+
+        Ok(Via::Statements(expr))
+    }
+
+    /// Body of `write_raw` for registers which can update selected bits without reading them first.
+    /// CSRs clear the bits that become zero and then set the ones that become one,
+    /// so the bits outside of `mask` are never touched.
+    pub fn masked_writer(
+        &self,
+        count: Option<u64>,
+        stride: u32,
+    ) -> syn::Result<Option<TokenStream2>> {
+        match self {
+            Self::Csr(_) => {
+                let write = self.per_register(count, |n| {
+                    let csr = self.csr(n, stride)?;
+                    let clear = self.template(format!("csrc {:#x}, {{}}", csr));
+                    let set = self.template(format!("csrs {:#x}, {{}}", csr));
+                    Ok(quote! {
+                        unsafe {
+                            core::arch::asm!(#clear, in(reg) clear, options(nostack));
+                            core::arch::asm!(#set, in(reg) set, options(nostack));
+                        }
+                    })
+                })?;
+
+                Ok(Some(quote! {
+                    let clear = (mask & !new_value) as usize;
+                    let set = new_value as usize;
+                    #write
+                }))
+            }
+            Self::SysReg(_) | Self::Msr(_) => Ok(None),
+        }
+    }
+
+    /// Expands `instruction` once for plain registers. Indexed registers get one expansion
+    /// per family member, selected with `index`, as instructions can't take register name at runtime.
+    fn per_register(
+        &self,
+        count: Option<u64>,
+        instruction: impl Fn(Option<u64>) -> syn::Result<TokenStream2>,
+    ) -> syn::Result<TokenStream2> {
+        match count {
+            None => instruction(None),
+            Some(count) => {
+                let arms = (0..count)
+                    .map(|n| {
+                        let body = instruction(Some(n))?;
+                        let n = LitInt::new(&n.to_string(), self.span());
+                        Ok(quote! { #n => #body })
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+
+                Ok(quote! {
+                    match index {
+                        #(#arms)*
+                        _ => unreachable!(),
+                    }
+                })
+            }
+        }
+    }
+
+    fn sysreg_name(&self, n: Option<u64>) -> syn::Result<String> {
+        match (self, n) {
+            (Self::SysReg(name), Some(n)) if name.value().contains("<n>") => {
+                Ok(name.value().replace("<n>", &n.to_string()))
+            }
+            (Self::SysReg(name), None) if !name.value().contains("<n>") => Ok(name.value()),
+            (Self::SysReg(name), _) => Err(syn::Error::new(
+                name.span(),
+                "`<n>` placeholder in `sysreg` has to be used together with `index`",
+            )),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Address of `n`th CSR of the family, `stride` apart from each other.
+    fn csr(&self, n: Option<u64>, stride: u32) -> syn::Result<u64> {
+        match self {
+            Self::Csr(csr) => {
+                let csr = csr.base10_parse::<u16>()? as u64 + n.unwrap_or(0) * u64::from(stride);
+                if csr > 0xfff {
+                    return Err(syn::Error::new(self.span(), "csr address out of range"));
+                }
+                Ok(csr)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Address of the MSR, `index`th of the family for indexed registers.
+    fn msr(&self, count: Option<u64>, stride: u32) -> syn::Result<TokenStream2> {
+        match self {
+            Self::Msr(msr) => {
+                let msr = msr.base10_parse::<u32>()?;
+                if let Some(count) = count {
+                    let last = u64::from(msr) + count.saturating_sub(1) * u64::from(stride);
+                    if last > u64::from(u32::MAX) {
+                        return Err(syn::Error::new(self.span(), "msr address out of range"));
+                    }
+                }
+                Ok(match count {
+                    None => quote! { #msr },
+                    Some(_) if stride == 1 => quote! { #msr + index as u32 },
+                    Some(_) => quote! { #msr + index as u32 * #stride },
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn template(&self, template: String) -> LitStr {
+        LitStr::new(&template, self.span())
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            Self::SysReg(name) => name.span(),
+            Self::Csr(lit) | Self::Msr(lit) => lit.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::{quote, ToTokens};
+    use syn::parse_quote;
+
+    use super::ArchRegister;
+    use crate::top_level_macro_arguments::Via;
+
+    fn expand(via: Via) -> String {
+        match via {
+            Via::Statements(expr) => expr.to_token_stream().to_string(),
+            Via::Call(_) => panic!("architecture registers expand to statements"),
+        }
+    }
+
+    #[test]
+    fn sysreg_expands_to_mrs_and_msr() {
+        let reg = ArchRegister::SysReg(parse_quote! { "daif" });
+
+        assert_eq!(
+            expand(reg.read_via(&parse_quote! { u64 }, None, 1).unwrap()),
+            quote! {
+                {
+                    let raw: u64;
+                    unsafe {
+                        core::arch::asm!("mrs {}, daif", out(reg) raw, options(nomem, nostack));
+                    }
+                    value = raw as u64;
+                }
+            }
+            .to_string()
+        );
+        assert_eq!(
+            expand(reg.write_via(None, 1).unwrap()),
+            quote! {
+                unsafe {
+                    core::arch::asm!("msr daif, {}", in(reg) value as u64, options(nostack));
+                }
+            }
+            .to_string()
+        );
+        assert!(reg.masked_writer(None, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn csr_expands_to_csr_instructions() {
+        let reg = ArchRegister::Csr(parse_quote! { 0x300 });
+
+        assert_eq!(
+            expand(reg.read_via(&parse_quote! { u32 }, None, 1).unwrap()),
+            quote! {
+                {
+                    let raw: usize;
+                    unsafe {
+                        core::arch::asm!("csrr {}, 0x300", out(reg) raw, options(nomem, nostack));
+                    }
+                    value = raw as u32;
+                }
+            }
+            .to_string()
+        );
+        assert_eq!(
+            expand(reg.write_via(None, 1).unwrap()),
+            quote! {
+                unsafe {
+                    core::arch::asm!("csrw 0x300, {}", in(reg) value as usize, options(nostack));
+                }
+            }
+            .to_string()
+        );
+        assert_eq!(
+            reg.masked_writer(None, 1).unwrap().unwrap().to_string(),
+            quote! {
+                let clear = (mask & !new_value) as usize;
+                let set = new_value as usize;
+                unsafe {
+                    core::arch::asm!("csrc 0x300, {}", in(reg) clear, options(nostack));
+                    core::arch::asm!("csrs 0x300, {}", in(reg) set, options(nostack));
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn msr_expands_to_rdmsr_and_wrmsr() {
+        let reg = ArchRegister::Msr(parse_quote! { 0x1B });
+
+        assert_eq!(
+            expand(reg.read_via(&parse_quote! { u64 }, None, 1).unwrap()),
+            quote! {
+                {
+                    let lo: u32;
+                    let hi: u32;
+                    unsafe {
+                        core::arch::asm!(
+                            "rdmsr",
+                            in("ecx") 27u32,
+                            out("eax") lo,
+                            out("edx") hi,
+                            options(nomem, nostack, preserves_flags)
+                        );
+                    }
+                    value = (((hi as u64) << 32) | lo as u64) as u64;
+                }
+            }
+            .to_string()
+        );
+        assert_eq!(
+            expand(reg.write_via(None, 1).unwrap()),
+            quote! {
+                unsafe {
+                    core::arch::asm!(
+                        "wrmsr",
+                        in("ecx") 27u32,
+                        in("eax") value as u64 as u32,
+                        in("edx") ((value as u64) >> 32) as u32,
+                        options(nostack, preserves_flags)
+                    );
+                }
+            }
+            .to_string()
+        );
+        assert!(reg.masked_writer(None, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn indexed_registers_expand_per_family_member() {
+        let reg = ArchRegister::SysReg(parse_quote! { "dbgbvr<n>_el1" });
+
+        assert_eq!(
+            expand(reg.write_via(Some(2), 1).unwrap()),
+            quote! {
+                match index {
+                    0 => unsafe {
+                        core::arch::asm!("msr dbgbvr0_el1, {}", in(reg) value as u64, options(nostack));
+                    }
+                    1 => unsafe {
+                        core::arch::asm!("msr dbgbvr1_el1, {}", in(reg) value as u64, options(nostack));
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            .to_string()
+        );
+        assert!(reg.write_via(None, 1).is_err());
+
+        let reg = ArchRegister::Csr(parse_quote! { 0x3b0 });
+
+        assert_eq!(
+            expand(reg.read_via(&parse_quote! { u64 }, Some(2), 1).unwrap()),
+            quote! {
+                {
+                    let raw: usize;
+                    match index {
+                        0 => unsafe {
+                            core::arch::asm!("csrr {}, 0x3b0", out(reg) raw, options(nomem, nostack));
+                        }
+                        1 => unsafe {
+                            core::arch::asm!("csrr {}, 0x3b1", out(reg) raw, options(nomem, nostack));
+                        }
+                        _ => unreachable!(),
+                    }
+                    value = raw as u64;
+                }
+            }
+            .to_string()
+        );
+
+        let reg = ArchRegister::Msr(parse_quote! { 0x200 });
+
+        assert_eq!(
+            expand(reg.write_via(Some(8), 1).unwrap()),
+            quote! {
+                unsafe {
+                    core::arch::asm!(
+                        "wrmsr",
+                        in("ecx") 512u32 + index as u32,
+                        in("eax") value as u64 as u32,
+                        in("edx") ((value as u64) >> 32) as u32,
+                        options(nostack, preserves_flags)
+                    );
+                }
+            }
+            .to_string()
+        );
+
+        assert_eq!(
+            expand(reg.write_via(Some(8), 2).unwrap()),
+            quote! {
+                unsafe {
+                    core::arch::asm!(
+                        "wrmsr",
+                        in("ecx") 512u32 + index as u32 * 2u32,
+                        in("eax") value as u64 as u32,
+                        in("edx") ((value as u64) >> 32) as u32,
+                        options(nostack, preserves_flags)
+                    );
+                }
+            }
+            .to_string()
+        );
+
+        let reg = ArchRegister::Csr(parse_quote! { 0x3a0 });
+
+        assert!(
+            expand(reg.read_via(&parse_quote! { u64 }, Some(2), 2).unwrap())
+                .contains("\"csrr {}, 0x3a2\"")
+        );
+        assert!(reg.write_via(Some(2), 0x1000).is_err());
+    }
+}
@@ -1,17 +1,22 @@
 use convert_case::{Case, Casing};
 use proc_macro2::{TokenStream as TokenStream2, TokenStream};
-use quote::{quote, ToTokens};
-use syn::{parse2, Attribute, Ident, ItemEnum, Visibility};
+use quote::quote;
+use syn::{parse2, Attribute, Error, Ident, ItemEnum, Visibility};
 
 use crate::{
     bit_field::BitField,
+    common,
     top_level_macro_arguments::{
         GlobalReadOnly,
         GlobalReadWrite,
         GlobalWriteOnly,
         Implementation,
+        IndexArg,
         KindArg,
+        Rmw,
+        Storage,
         TopLevelMacroArguments,
+        Via,
     },
 };
 
@@ -25,22 +30,45 @@ pub struct BitAccess {
 
 impl BitAccess {
     pub fn new(args: TokenStream2, item: ItemEnum) -> syn::Result<Self> {
+        let top_level_arguments = parse2::<TopLevelMacroArguments>(args)?;
+        let fields = BitField::many(item.variants)?;
+        if let Some(width) = common::bit_width(&top_level_arguments.base_type) {
+            for field in &fields {
+                if field.split_end().is_some_and(|end| end > u64::from(width)) {
+                    return Err(Error::new(
+                        field.ident.span(),
+                        format!(
+                            "pieces of split field `{}` don't fit in base_type",
+                            field.ident
+                        ),
+                    ));
+                }
+            }
+        }
+        if !top_level_arguments.iter_set {
+            if let Some((_, span)) = fields.iter().find_map(|field| field.priority) {
+                return Err(Error::new(
+                    span,
+                    "priority attribute requires `iter_set = true` in top level attribute",
+                ));
+            }
+        }
+
         Ok(Self {
-            top_level_arguments: parse2::<TopLevelMacroArguments>(args)?,
+            top_level_arguments,
             struct_identifier: item.ident,
             struct_visibility: item.vis,
-            fields: BitField::many(item.variants)?,
+            fields,
             attributes: item.attrs,
         })
     }
 
     pub fn into_token_stream(self) -> TokenStream2 {
-        let enum_field_names = self.enum_field_names();
         let private_module_ident = self.private_module_ident();
         let private_struct_ident = self.private_struct_ident();
         let main_struct_const_fields = self.main_struct_const_fields();
         let field_inline_variant_enums = self.field_inline_variant_enums();
-        let read_write_impls = self.read_write_impls(&enum_field_names);
+        let read_write_impls = self.read_write_impls();
         let representation_ident = self.representation_struct_ident();
         let private_struct = self.private_struct_definition(&private_struct_ident);
         let main_struct_constructors =
@@ -48,27 +76,49 @@ impl BitAccess {
         let immutable_representation_struct =
             self.immutable_representation_struct(&representation_ident);
         let immutable_representation_private =
-            self.immutable_representation_private(&representation_ident, &enum_field_names);
+            self.immutable_representation_private(&representation_ident);
 
         let read_raw_fn = self.read_raw_fn();
         let write_raw_fn = self.write_raw_fn();
+        let verify_fns = self.verify_fns(&representation_ident);
+        let wait_fns = self.wait_fns(&representation_ident);
+        let fenced_fns = self.fenced_fns();
+        let validate_fn = self.validate_fn();
+        let try_write_fn = self.try_write_fn();
+        let fields_const = self.fields_const();
+        let flag_enum = self.flag_enum();
+        let inline_iter_set = self.inline_iter_set();
 
         let structure = self.structure();
 
         let vis = &self.struct_visibility;
         let ident = &self.struct_identifier;
         let attributes = &self.attributes;
+        let bus_param = self.bus_param();
+        let bus_args = self.bus_args();
+        // Field consts don't depend on the bus, `()` lets them be used as `Register::Field`
+        let const_args = self
+            .top_level_arguments
+            .bus
+            .as_ref()
+            .map(|_| quote! { <()> });
 
         let private_api = quote! {
             #vis mod #private_module_ident {
                 #private_struct
                 #read_write_impls
 
-                impl super::#ident {
+                impl<#bus_param> super::#ident #bus_args {
                     #main_struct_constructors
 
                     #read_raw_fn
                     #write_raw_fn
+                    #verify_fns
+                    #wait_fns
+                    #fenced_fns
+                    #validate_fn
+                    #try_write_fn
+                    #inline_iter_set
                 }
 
                 #immutable_representation_private
@@ -80,12 +130,15 @@ impl BitAccess {
             #structure
 
             #[allow(non_upper_case_globals)]
-            impl #ident {
+            impl #ident #const_args {
                 #(#main_struct_const_fields)*
+                #fields_const
             }
 
             #(#field_inline_variant_enums)*
 
+            #flag_enum
+
             #immutable_representation_struct
         };
 
@@ -110,16 +163,32 @@ impl BitAccess {
                     }
                 }
             }
-            _ => quote! {
-                #vis struct #ident;
+            _ if self.top_level_arguments.bus.is_some() => quote! {
+                #vis struct #ident<BUS> {
+                    bus: core::cell::RefCell<BUS>,
+                }
+            },
+            _ => match &self.top_level_arguments.index {
+                Some(IndexArg { ty, .. }) => quote! {
+                    #vis struct #ident {
+                        index: #ty,
+                    }
+                },
+                None => quote! {
+                    #vis struct #ident;
+                },
             },
         }
     }
 
     fn private_struct_definition(&self, private_struct_ident: &Ident) -> TokenStream {
         let base_type = &self.top_level_arguments.base_type;
-        let private_value_holder = match self.top_level_arguments.implementation {
-            Implementation::Inline(_) => Some(quote! { value: #base_type }),
+        let private_value_holder = match (
+            &self.top_level_arguments.implementation,
+            &self.top_level_arguments.storage,
+        ) {
+            (Implementation::Inline(_), Storage::Atomic(atomic)) => Some(quote! { value: #atomic }),
+            (Implementation::Inline(_), Storage::Plain) => Some(quote! { value: #base_type }),
             _ => None,
         };
         let attributes = &self.attributes;
@@ -143,18 +212,36 @@ impl BitAccess {
         )
     }
 
-    fn read_write_impls(&self, enum_field_names: &[Ident]) -> TokenStream2 {
+    fn read_write_impls(&self) -> TokenStream2 {
         let ident = &self.struct_identifier;
         let base_type = &self.top_level_arguments.base_type;
+        let bus_param = self.bus_param();
+        let bus_args = self.bus_args();
 
-        let read_impl = self.read_impl(enum_field_names.iter()).map(|implementation| quote! {
-            impl<F: bitaccess::FieldAccess<#base_type>> bitaccess::ReadBits<#base_type, F> for super::#ident {
+        if self.top_level_arguments.is_async() {
+            return self.async_read_write_impls();
+        }
+
+        let read_impl = self.read_impl().map(|implementation| quote! {
+            impl<F: bitaccess::FieldAccess<#base_type>, #bus_param> bitaccess::ReadBits<#base_type, F> for super::#ident #bus_args {
                 #implementation
             }
         });
-        let write_impl = self.write_impl(enum_field_names.iter()).map(|implementation| quote! {
-            impl<F: bitaccess::FieldAccess<#base_type>> bitaccess::WriteBits<#base_type, F> for super::#ident {
-                #implementation
+        let write_impl = self.write_impl().map(|implementation| {
+            if self.top_level_arguments.is_atomic() {
+                let compare_exchange = self.compare_exchange_fields_fn();
+                quote! {
+                    impl<F: bitaccess::FieldAccess<#base_type>> bitaccess::AtomicWriteBits<#base_type, F> for super::#ident {
+                        #implementation
+                        #compare_exchange
+                    }
+                }
+            } else {
+                quote! {
+                    impl<F: bitaccess::FieldAccess<#base_type>, #bus_param> bitaccess::WriteBits<#base_type, F> for super::#ident #bus_args {
+                        #implementation
+                    }
+                }
             }
         });
 
@@ -183,6 +270,114 @@ impl BitAccess {
             .collect()
     }
 
+    fn fields_const(&self) -> TokenStream2 {
+        let vis = &self.struct_visibility;
+        let base_type = &self.top_level_arguments.base_type;
+        let fields = self.fields.iter().map(BitField::field_info);
+
+        quote! {
+            /// Names and masks of all fields, in declaration order.
+            #vis const FIELDS: &'static [bitaccess::FieldInfo<#base_type>] = &[#(#fields),*];
+        }
+    }
+
+    fn flag_enum_ident(&self) -> Ident {
+        Ident::new(
+            &format!(
+                "{}Flag",
+                self.struct_identifier.to_string().to_case(Case::Pascal)
+            ),
+            self.struct_identifier.span(),
+        )
+    }
+
+    /// Single-bit fields in `iter_set` order - by priority first, then by bit.
+    /// Empty unless the register opts in with `iter_set = true`.
+    fn flags(&self) -> Vec<(&Ident, u64)> {
+        if !self.top_level_arguments.iter_set {
+            return Vec::new();
+        }
+        let mut flags: Vec<_> = self
+            .fields
+            .iter()
+            .filter_map(|field| field.flag_offset().map(|offset| (field, offset)))
+            .collect();
+        flags.sort_by_key(|(field, offset)| {
+            let priority = field.priority.map(|(priority, _)| priority);
+            (priority.is_none(), priority, *offset)
+        });
+
+        flags
+            .into_iter()
+            .map(|(field, offset)| (&field.ident, offset))
+            .collect()
+    }
+
+    /// Enum of single-bit fields, yielded by `iter_set`.
+    fn flag_enum(&self) -> Option<TokenStream2> {
+        let flags = self.flags();
+        if flags.is_empty() {
+            return None;
+        }
+        let vis = &self.struct_visibility;
+        let base_type = &self.top_level_arguments.base_type;
+        let flag_ident = self.flag_enum_ident();
+        let count = flags.len();
+        let (idents, offsets): (Vec<_>, Vec<_>) = flags.into_iter().unzip();
+
+        Some(quote! {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #vis enum #flag_ident {
+                #(#idents),*
+            }
+
+            impl #flag_ident {
+                const ORDER: [(Self, #base_type); #count] = [#((Self::#idents, 1 << #offsets)),*];
+
+                #vis fn mask(self) -> #base_type {
+                    match self {
+                        #(Self::#idents => 1 << #offsets),*
+                    }
+                }
+
+                /// Flags set in `value`, ordered by priority, then by bit.
+                #vis fn iter_set(value: #base_type) -> impl Iterator<Item = Self> {
+                    Self::ORDER
+                        .into_iter()
+                        .filter(move |(_, mask)| value & mask != 0)
+                        .map(|(flag, _)| flag)
+                }
+            }
+        })
+    }
+
+    fn iter_set_fn(&self, value: TokenStream2) -> Option<TokenStream2> {
+        if self.flags().is_empty() {
+            return None;
+        }
+        let vis = &self.struct_visibility;
+        let flag_ident = self.flag_enum_ident();
+
+        Some(quote! {
+            /// Single-bit fields set in the register, ordered by priority, then by bit.
+            #vis fn iter_set(&self) -> impl Iterator<Item = super::#flag_ident> {
+                super::#flag_ident::iter_set(#value)
+            }
+        })
+    }
+
+    /// Inline registers without representation list their flags directly.
+    fn inline_iter_set(&self) -> Option<TokenStream2> {
+        match &self.top_level_arguments.implementation {
+            Implementation::Inline(KindArg { read, .. })
+                if *read && !self.top_level_arguments.is_atomic() =>
+            {
+                self.iter_set_fn(quote! { self.inner.value })
+            }
+            _ => None,
+        }
+    }
+
     fn private_struct_ident(&self) -> Ident {
         Ident::new(
             &format!("{}Private", &self.struct_identifier),
@@ -197,19 +392,17 @@ impl BitAccess {
         )
     }
 
-    fn enum_field_names(&self) -> Vec<Ident> {
+    fn field_matchers(&self) -> Vec<TokenStream2> {
         self.fields
             .iter()
-            .map(|field| Ident::new(&field.ident.to_string(), field.ident.span()))
+            .map(|field| field.matcher(&self.struct_identifier))
             .collect()
     }
 
-    fn read_impl<'a>(
-        &self,
-        enum_field_names: impl Iterator<Item = &'a Ident>,
-    ) -> Option<TokenStream2> {
+    fn read_impl(&self) -> Option<TokenStream2> {
         if self.top_level_arguments.is_read() {
             let readers: Vec<_> = self.fields.iter().map(|item| item.reader()).collect();
+            let matchers = self.field_matchers();
             let base_type = &self.top_level_arguments.base_type;
 
             Some(quote! {
@@ -220,7 +413,7 @@ impl BitAccess {
                 ) -> bitaccess::Field<#base_type, F> {
                     let value = self.read_raw();
                     bitaccess::Field::new(match bits.mask() {
-                        #(_ if Self::#enum_field_names.mask() == bits.mask() => #readers,)*
+                        #(_ if #matchers => #readers,)*
                         _ => panic!("use provided consts to read from register"),
                     })
                 }
@@ -230,14 +423,11 @@ impl BitAccess {
         }
     }
 
-    fn immutable_representation_read_impl<'a>(
-        &self,
-        enum_field_names: impl Iterator<Item = &'a Ident>,
-    ) -> TokenStream2 {
+    fn immutable_representation_read_impl(&self) -> TokenStream2 {
         let readers: Vec<_> = self.fields.iter().map(|item| item.reader()).collect();
+        let matchers = self.field_matchers();
         let vis = &self.struct_visibility;
         let base_type = &self.top_level_arguments.base_type;
-        let ident = &self.struct_identifier;
 
         quote! {
             #[allow(unreachable_code)]
@@ -247,31 +437,34 @@ impl BitAccess {
             ) -> bitaccess::Field<#base_type, F> {
                 let value = self.read_raw();
                 bitaccess::Field::new(match bits.mask() {
-                    #(_ if super::#ident::#enum_field_names.mask() == bits.mask() => #readers,)*
+                    #(_ if #matchers => #readers,)*
                     _ => panic!("use provided consts to read from register"),
                 })
             }
         }
     }
 
-    fn write_impl<'a>(
-        &self,
-        enum_field_names: impl Iterator<Item = &'a Ident>,
-    ) -> Option<TokenStream2> {
+    fn write_impl(&self) -> Option<TokenStream2> {
         if self.top_level_arguments.is_write() {
             let writers: Vec<_> = self.fields.iter().map(|item| item.writer()).collect();
+            let matchers = self.field_matchers();
             let base_type = &self.top_level_arguments.base_type;
+            let receiver = if self.top_level_arguments.is_atomic() {
+                quote! { &self }
+            } else {
+                quote! { &mut self }
+            };
 
             Some(quote! {
                 #[allow(unreachable_code)]
                 fn write(
-                    &mut self,
+                    #receiver,
                     bits: bitaccess::FieldDefinition<#base_type, F>,
                     new_value: impl Into<bitaccess::Field<#base_type, F>>
                 ) {
                     let new_value: bitaccess::Field<#base_type, F> = new_value.into();
                     match bits.mask() {
-                        #(_ if Self::#enum_field_names.mask() == bits.mask() => #writers,)*
+                        #(_ if #matchers => #writers,)*
                         _ => panic!("use provided consts to write to register"),
                     }
                 }
@@ -281,14 +474,11 @@ impl BitAccess {
         }
     }
 
-    fn immutable_representation_write_impl<'a>(
-        &self,
-        enum_field_names: impl Iterator<Item = &'a Ident>,
-    ) -> TokenStream2 {
+    fn immutable_representation_write_impl(&self) -> TokenStream2 {
         let writers: Vec<_> = self.fields.iter().map(|item| item.writer()).collect();
+        let matchers = self.field_matchers();
         let vis = &self.struct_visibility;
         let base_type = &self.top_level_arguments.base_type;
-        let ident = &self.struct_identifier;
 
         quote! {
             #[allow(unreachable_code)]
@@ -299,7 +489,7 @@ impl BitAccess {
             ) {
                 let new_value: bitaccess::Field<#base_type, F> = new_value.into();
                 match bits.mask() {
-                    #(_ if super::#ident::#enum_field_names.mask() == bits.mask() => #writers,)*
+                    #(_ if #matchers => #writers,)*
                     _ => panic!("use provided consts to write to register"),
                 }
             }
@@ -316,33 +506,151 @@ impl BitAccess {
         match &self.top_level_arguments.implementation {
             Implementation::Inline(_) => {
                 let base_type = &self.top_level_arguments.base_type;
-                quote! {
-                    #vis fn new() -> Self {
-                        Self { inner: #private_struct_ident { value: 0 } }
+                let range_checks = self
+                    .fields
+                    .iter()
+                    .filter_map(|field| field.stored_range_check(&self.struct_identifier));
+                let try_from_value = quote! {
+                    /// Register holding `value`, rejected when a field lies outside its `range`.
+                    #vis fn try_from_value(value: #base_type) -> Result<Self, bitaccess::InvalidValue<#base_type>> {
+                        #(#range_checks)*
+                        Ok(Self::from_value(value))
                     }
+                };
+                match &self.top_level_arguments.storage {
+                    Storage::Plain => quote! {
+                        #vis fn new() -> Self {
+                            Self { inner: #private_struct_ident { value: 0 } }
+                        }
 
-                    #vis fn from_value(value: #base_type) -> Self {
-                        Self { inner: #private_struct_ident { value, }, }
-                    }
-                }
-            }
-            Implementation::GlobalWriteOnly(_) => {
-                quote! {
-                    #vis fn new() -> Self {
-                        Self
-                    }
+                        #vis fn from_value(value: #base_type) -> Self {
+                            Self { inner: #private_struct_ident { value, }, }
+                        }
+
+                        #try_from_value
+                    },
+                    Storage::Atomic(atomic) => quote! {
+                        #vis fn new() -> Self {
+                            Self { inner: #private_struct_ident { value: #atomic::new(0) } }
+                        }
+
+                        #vis fn from_value(value: #base_type) -> Self {
+                            Self { inner: #private_struct_ident { value: #atomic::new(value) }, }
+                        }
+
+                        #try_from_value
+
+                        #vis fn fetch(&self) -> super::#representation_ident {
+                            super::#representation_ident::new(self.read_raw())
+                        }
+
+                        /// Applies `f` to a snapshot of the register and stores the result,
+                        /// retrying with a fresh snapshot when the register was changed in the meantime.
+                        #vis fn modify(&self, mut f: impl FnMut(&mut super::#representation_ident)) {
+                            let _ = self.inner.value.fetch_update(
+                                core::sync::atomic::Ordering::AcqRel,
+                                core::sync::atomic::Ordering::Acquire,
+                                |value| {
+                                    let mut representation = super::#representation_ident::new(value);
+                                    f(&mut representation);
+                                    Some(representation.get())
+                                },
+                            );
+                        }
+                    },
                 }
             }
             _ => {
-                quote! {
-                    #vis fn new() -> Self {
-                        Self
+                let constructors = match &self.top_level_arguments.index {
+                    _ if self.top_level_arguments.bus.is_some() => quote! {
+                        #vis fn new(bus: BUS) -> Self {
+                            Self { bus: core::cell::RefCell::new(bus) }
+                        }
+
+                        /// Gives the bus back.
+                        #vis fn release(self) -> BUS {
+                            self.bus.into_inner()
+                        }
+                    },
+                    Some(IndexArg { ty, count }) => quote! {
+                        #vis const COUNT: #ty = #count;
+
+                        #vis const fn at(index: #ty) -> Self {
+                            assert!(index < Self::COUNT, "register index out of bounds");
+                            Self { index }
+                        }
+
+                        #vis fn try_at(index: #ty) -> Option<Self> {
+                            if index < Self::COUNT {
+                                Some(Self { index })
+                            } else {
+                                None
+                            }
+                        }
+
+                        #vis fn index(&self) -> #ty {
+                            self.index
+                        }
+                    },
+                    None => quote! {
+                        #vis fn new() -> Self {
+                            Self
+                        }
+                    },
+                };
+                let asyncness = self.asyncness();
+                let await_ = self.await_();
+                let modify = match &self.top_level_arguments.implementation {
+                    Implementation::GlobalReadWrite(_) => {
+                        let modify = self.rmw_guard(quote! {
+                            let mut representation = super::#representation_ident::new(self.read_raw()#await_);
+                            f(&mut representation);
+                            self.set(representation.get())#await_;
+                        });
+                        Some(quote! {
+                            /// Reads the register once, applies all writes done by `f` and stores the result.
+                            #vis #asyncness fn modify(&mut self, f: impl FnOnce(&mut super::#representation_ident)) {
+                                #modify
+                            }
+                        })
+                    }
+                    _ => None,
+                };
+                let fetch = match (
+                    &self.top_level_arguments.implementation,
+                    &self.top_level_arguments.index,
+                ) {
+                    (Implementation::GlobalWriteOnly(_), _) => None,
+                    (_, None) if self.top_level_arguments.bus.is_none() => Some(quote! {
+                        #vis fn fetch() -> super::#representation_ident {
+                            let me = Self::new();
+                            super::#representation_ident::new(me.read_raw())
+                        }
+                    }),
+                    // Registers with state have to be fetched through an instance
+                    _ => {
+                        let receiver = self.receiver();
+                        Some(quote! {
+                            #vis #asyncness fn fetch(#receiver) -> super::#representation_ident {
+                                super::#representation_ident::new(self.read_raw()#await_)
+                            }
+                        })
                     }
+                };
 
-                    #vis fn fetch() -> super::#representation_ident {
-                        let me = Self::new();
-                        super::#representation_ident::new(me.read_raw())
+                let accessors = if self.top_level_arguments.error.is_some() {
+                    self.fallible_accessors(representation_ident)
+                } else {
+                    quote! {
+                        #fetch
+                        #modify
                     }
+                };
+
+                quote! {
+                    #constructors
+
+                    #accessors
                 }
             }
         }
@@ -355,7 +663,7 @@ impl BitAccess {
         let base_type = &self.top_level_arguments.base_type;
 
         match self.top_level_arguments.implementation {
-            Implementation::Inline(_) => None,
+            Implementation::Inline(_) if !self.top_level_arguments.is_atomic() => None,
             _ => Some(quote! {
                 #vis struct #representation_ident {
                     value: #base_type,
@@ -366,14 +674,14 @@ impl BitAccess {
     fn immutable_representation_private(
         &self,
         representation_ident: &Ident,
-        enum_field_names: &[Ident],
     ) -> Option<TokenStream2> {
         let base_type = &self.top_level_arguments.base_type;
-        let read_impl = self.immutable_representation_read_impl(enum_field_names.iter());
-        let write_impl = self.immutable_representation_write_impl(enum_field_names.iter());
+        let read_impl = self.immutable_representation_read_impl();
+        let write_impl = self.immutable_representation_write_impl();
+        let iter_set = self.iter_set_fn(quote! { self.value });
 
         match self.top_level_arguments.implementation {
-            Implementation::Inline(_) => None,
+            Implementation::Inline(_) if !self.top_level_arguments.is_atomic() => None,
             _ => Some(quote! {
                     impl super::#representation_ident {
                         pub fn new(value: #base_type) -> Self {
@@ -392,6 +700,7 @@ impl BitAccess {
 
                         #read_impl
                         #write_impl
+                        #iter_set
 
                         pub fn get(&self) -> #base_type {
                             self.value
@@ -400,77 +709,931 @@ impl BitAccess {
             }),
         }
     }
+    /// `BUS` generic parameter of registers holding a bus.
+    fn bus_param(&self) -> Option<TokenStream2> {
+        self.top_level_arguments.bus.as_ref().map(|bus| {
+            let bound = bus.bound();
+            quote! { BUS: #bound }
+        })
+    }
+
+    fn bus_args(&self) -> Option<TokenStream2> {
+        self.top_level_arguments
+            .bus
+            .as_ref()
+            .map(|_| quote! { <BUS> })
+    }
+
+    fn asyncness(&self) -> Option<TokenStream2> {
+        self.top_level_arguments
+            .is_async()
+            .then(|| quote! { async })
+    }
+
+    fn await_(&self) -> Option<TokenStream2> {
+        self.top_level_arguments
+            .is_async()
+            .then(|| quote! { .await })
+    }
+
+    /// Receiver of reading accessors. Asynchronous registers can't borrow the bus
+    /// for the duration of an `.await`, so they need exclusive access.
+    fn receiver(&self) -> TokenStream2 {
+        if self.top_level_arguments.is_async() {
+            quote! { &mut self }
+        } else {
+            quote! { &self }
+        }
+    }
+
+    /// `AsyncReadBits` and `AsyncWriteBits` implementations. Fields are decoded from and encoded
+    /// into the representation, so only whole register accesses need to be awaited.
+    fn async_read_write_impls(&self) -> TokenStream2 {
+        let ident = &self.struct_identifier;
+        let base_type = &self.top_level_arguments.base_type;
+        let representation_ident = self.representation_struct_ident();
+        let bus_param = self.bus_param();
+        let bus_args = self.bus_args();
+
+        let read_impl = self.top_level_arguments.is_read().then(|| quote! {
+            impl<F: bitaccess::FieldAccess<#base_type>, #bus_param> bitaccess::AsyncReadBits<#base_type, F> for super::#ident #bus_args {
+                async fn read(
+                    &mut self,
+                    bits: bitaccess::FieldDefinition<#base_type, F>
+                ) -> bitaccess::Field<#base_type, F> {
+                    super::#representation_ident::new(self.read_raw().await).read(bits)
+                }
+            }
+        });
+        let write_impl = self.top_level_arguments.is_write().then(|| quote! {
+            impl<F: bitaccess::FieldAccess<#base_type>, #bus_param> bitaccess::AsyncWriteBits<#base_type, F> for super::#ident #bus_args {
+                async fn write(
+                    &mut self,
+                    bits: bitaccess::FieldDefinition<#base_type, F>,
+                    new_value: impl Into<bitaccess::Field<#base_type, F>>
+                ) {
+                    let mask = bits.mask();
+                    let mut representation = super::#representation_ident::new(0);
+                    representation.write_to_cache(bits, new_value);
+                    self.write_raw(representation.get(), mask).await
+                }
+            }
+        });
+
+        quote! {
+            #read_impl
+            #write_impl
+        }
+    }
+
+    fn is_indexed(&self) -> bool {
+        self.top_level_arguments.index.is_some()
+    }
+
+    fn index_binding(&self) -> Option<TokenStream2> {
+        self.is_indexed()
+            .then(|| quote! { let index = self.index; })
+    }
+
     fn read_raw_fn(&self) -> Option<TokenStream2> {
+        if self.top_level_arguments.error.is_some() {
+            return self.fallible_read_raw_fn();
+        }
+
         let read_via = match &self.top_level_arguments.implementation {
             Implementation::Inline(KindArg { read, .. }) if *read => {
-                Some(quote! { value = self.inner.value })
+                if self.top_level_arguments.is_atomic() {
+                    Some(quote! {
+                        value = self.inner.value.load(core::sync::atomic::Ordering::Acquire);
+                    })
+                } else {
+                    Some(quote! { value = self.inner.value; })
+                }
             }
             Implementation::GlobalReadOnly(box GlobalReadOnly { read_via }) => {
-                Some(read_via.to_token_stream())
+                Some(self.global_reader(read_via))
             }
             Implementation::GlobalReadWrite(box GlobalReadWrite { read_via, .. }) => {
-                Some(read_via.to_token_stream())
+                Some(self.global_reader(read_via))
             }
             Implementation::GlobalWriteOnly(_) | Implementation::Inline(_) => None,
         };
         read_via.map(|read_via| {
             let base_type = &self.top_level_arguments.base_type;
             let vis = &self.struct_visibility;
+            let index_binding = self.index_binding();
+            let asyncness = self.asyncness();
+            let await_ = self.await_();
+            let receiver = self.receiver();
 
             quote! {
-                fn read_raw(&self) -> #base_type {
+                #[allow(clippy::redundant_closure_call)]
+                #asyncness fn read_raw(#receiver) -> #base_type {
+                    #index_binding
                     let mut value: #base_type;
-                    #read_via;
+                    #read_via
                     value
                 }
 
-                #vis fn get(&self) -> #base_type {
-                    self.read_raw()
+                #vis #asyncness fn get(#receiver) -> #base_type {
+                    self.read_raw()#await_
                 }
             }
         })
     }
 
     fn write_raw_fn(&self) -> Option<TokenStream2> {
+        if self.top_level_arguments.is_atomic() {
+            return self.atomic_write_raw_fn();
+        }
+        if self.top_level_arguments.error.is_some() {
+            return self.fallible_write_raw_fn();
+        }
+
         let write_via = match &self.top_level_arguments.implementation {
             Implementation::Inline(KindArg { write, .. }) if *write => {
-                Some(quote! { self.inner.value = value })
+                Some(quote! { self.inner.value = value; })
             }
             Implementation::GlobalReadWrite(box GlobalReadWrite { write_via, .. }) => {
-                Some(write_via.to_token_stream())
+                Some(self.global_writer(write_via))
             }
             Implementation::GlobalWriteOnly(box GlobalWriteOnly { write_via }) => {
-                Some(write_via.to_token_stream())
+                Some(self.global_writer(write_via))
             }
             Implementation::Inline(_) | Implementation::GlobalReadOnly(_) => None,
         };
         write_via.map(|write_via| {
             let base_type = &self.top_level_arguments.base_type;
             let vis = &self.struct_visibility;
+            let index_binding = self.index_binding();
+            let asyncness = self.asyncness();
+            let await_ = self.await_();
 
-            let write_raw = if self.top_level_arguments.is_read() {
+            let write_raw_ident = self.write_raw_ident();
+            let write_raw = if let Some(masked_write_via) =
+                &self.top_level_arguments.masked_write_via
+            {
+                let fence = self.top_level_arguments.ordering.fence();
+                let masked_write = self.rmw_guard(quote! {
+                    #fence
+                    #masked_write_via
+                });
                 quote! {
-                    fn write_raw(&mut self, new_value: #base_type, mask: #base_type) {
-                        let old_value = self.read_raw() & !(mask);
-                        let mut value = old_value | new_value;
-                        #write_via
+                    fn #write_raw_ident(&mut self, new_value: #base_type, mask: #base_type) {
+                        #index_binding
+                        #masked_write
+                    }
+                }
+            } else if self.top_level_arguments.is_read() {
+                let read_modify_write = self.rmw_guard(quote! {
+                    let old_value = self.read_raw()#await_ & !(mask);
+                    let mut value = old_value | new_value;
+                    #write_via
+                });
+                quote! {
+                    #[allow(clippy::redundant_closure_call)]
+                    #asyncness fn #write_raw_ident(&mut self, new_value: #base_type, mask: #base_type) {
+                        #index_binding
+                        #read_modify_write
                     }
                 }
             } else {
                 quote! {
-                    fn write_raw(&mut self, value: #base_type, _: #base_type) {
+                    #[allow(clippy::redundant_closure_call)]
+                    #asyncness fn write_raw(&mut self, value: #base_type, _: #base_type) {
+                        #index_binding
                         #write_via
                     }
                 }
             };
 
+            let verify = self.verify_hook(quote! { value }, quote! { !0 });
+
             quote! {
                 #write_raw
 
-                #vis fn set(&mut self, value: #base_type) {
+                #[allow(clippy::redundant_closure_call)]
+                #vis #asyncness fn set(&mut self, value: #base_type) {
+                    #index_binding
+                    { #write_via }
+                    #verify
+                }
+            }
+        })
+    }
+
+    /// `try_read_raw` of registers with fallible backend, `read_raw` panics on its errors.
+    fn fallible_read_raw_fn(&self) -> Option<TokenStream2> {
+        let read_via = match &self.top_level_arguments.implementation {
+            Implementation::GlobalReadOnly(box GlobalReadOnly { read_via })
+            | Implementation::GlobalReadWrite(box GlobalReadWrite { read_via, .. }) => read_via,
+            _ => return None,
+        };
+        let read_via = self.global_reader(read_via);
+        let base_type = &self.top_level_arguments.base_type;
+        let error = &self.top_level_arguments.error;
+        let vis = &self.struct_visibility;
+        let index_binding = self.index_binding();
+        let asyncness = self.asyncness();
+        let await_ = self.await_();
+        let receiver = self.receiver();
+
+        Some(quote! {
+            #[allow(clippy::redundant_closure_call)]
+            #asyncness fn try_read_raw(#receiver) -> Result<#base_type, #error> {
+                #index_binding
+                let mut value: #base_type;
+                #read_via
+                Ok(value)
+            }
+
+            #asyncness fn read_raw(#receiver) -> #base_type {
+                match self.try_read_raw()#await_ {
+                    Ok(value) => value,
+                    Err(error) => panic!("failed to read register: {:?}", error),
+                }
+            }
+
+            #vis #asyncness fn try_get(#receiver) -> Result<#base_type, #error> {
+                self.try_read_raw()#await_
+            }
+
+            #vis #asyncness fn get(#receiver) -> #base_type {
+                self.read_raw()#await_
+            }
+        })
+    }
+
+    /// `try_write_raw` of registers with fallible backend, `write_raw` panics on its errors.
+    fn fallible_write_raw_fn(&self) -> Option<TokenStream2> {
+        let write_via = match &self.top_level_arguments.implementation {
+            Implementation::GlobalWriteOnly(box GlobalWriteOnly { write_via })
+            | Implementation::GlobalReadWrite(box GlobalReadWrite { write_via, .. }) => write_via,
+            _ => return None,
+        };
+        let write_via = self.global_writer(write_via);
+        let base_type = &self.top_level_arguments.base_type;
+        let error = &self.top_level_arguments.error;
+        let vis = &self.struct_visibility;
+        let index_binding = self.index_binding();
+        let asyncness = self.asyncness();
+        let await_ = self.await_();
+
+        let write_raw_ident = self.write_raw_ident();
+
+        let try_write_raw = if self.top_level_arguments.is_read() {
+            let read_modify_write = self.rmw_guard(quote! {
+                let old_value = self.try_read_raw()#await_? & !(mask);
+                let mut value = old_value | new_value;
+                #write_via
+                Ok(())
+            });
+            quote! {
+                #asyncness fn try_write_raw(&mut self, new_value: #base_type, mask: #base_type) -> Result<(), #error> {
+                    #index_binding
+                    #read_modify_write
+                }
+            }
+        } else {
+            quote! {
+                #asyncness fn try_write_raw(&mut self, value: #base_type, _: #base_type) -> Result<(), #error> {
+                    #index_binding
                     #write_via
+                    Ok(())
+                }
+            }
+        };
+
+        Some(quote! {
+            #[allow(clippy::redundant_closure_call)]
+            #try_write_raw
+
+            #asyncness fn #write_raw_ident(&mut self, new_value: #base_type, mask: #base_type) {
+                if let Err(error) = self.try_write_raw(new_value, mask)#await_ {
+                    panic!("failed to write register: {:?}", error)
+                }
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            #vis #asyncness fn try_set(&mut self, value: #base_type) -> Result<(), #error> {
+                #index_binding
+                #write_via
+                Ok(())
+            }
+
+            #vis #asyncness fn set(&mut self, value: #base_type) {
+                if let Err(error) = self.try_set(value)#await_ {
+                    panic!("failed to write register: {:?}", error)
+                }
+            }
+        })
+    }
+
+    /// Field and whole register accessors of registers with fallible backend.
+    /// Fields are decoded from and encoded into the representation, as in `AsyncReadBits`.
+    fn fallible_accessors(&self, representation_ident: &Ident) -> TokenStream2 {
+        let base_type = &self.top_level_arguments.base_type;
+        let error = &self.top_level_arguments.error;
+        let vis = &self.struct_visibility;
+        let asyncness = self.asyncness();
+        let await_ = self.await_();
+        let receiver = self.receiver();
+        let stateless = !self.is_indexed() && self.top_level_arguments.bus.is_none();
+
+        let read = self.top_level_arguments.is_read().then(|| {
+            let fetch = if stateless {
+                quote! {
+                    #vis fn try_fetch() -> Result<super::#representation_ident, #error> {
+                        Self::new().try_read_raw().map(super::#representation_ident::new)
+                    }
+
+                    #vis fn fetch() -> super::#representation_ident {
+                        super::#representation_ident::new(Self::new().read_raw())
+                    }
+                }
+            } else {
+                quote! {
+                    #vis #asyncness fn try_fetch(#receiver) -> Result<super::#representation_ident, #error> {
+                        Ok(super::#representation_ident::new(self.try_read_raw()#await_?))
+                    }
+
+                    #vis #asyncness fn fetch(#receiver) -> super::#representation_ident {
+                        super::#representation_ident::new(self.read_raw()#await_)
+                    }
+                }
+            };
+
+            quote! {
+                #vis #asyncness fn try_read<F: bitaccess::FieldAccess<#base_type>>(
+                    #receiver,
+                    bits: bitaccess::FieldDefinition<#base_type, F>,
+                ) -> Result<bitaccess::Field<#base_type, F>, #error> {
+                    Ok(super::#representation_ident::new(self.try_read_raw()#await_?).read(bits))
+                }
+
+                #fetch
+            }
+        });
+        let write = self.top_level_arguments.is_write().then(|| {
+            quote! {
+                /// Writes the field, rejecting values which don't fit in it instead of truncating them.
+                #vis #asyncness fn try_write<F: bitaccess::FieldAccess<#base_type>>(
+                    &mut self,
+                    bits: bitaccess::FieldDefinition<#base_type, F>,
+                    new_value: impl Into<bitaccess::Field<#base_type, F>>,
+                ) -> Result<(), bitaccess::WriteError<#base_type, #error>> {
+                    let new_value: bitaccess::Field<#base_type, F> = new_value.into();
+                    Self::validate(&bits, &new_value)?;
+                    let mask = bits.mask();
+                    let mut representation = super::#representation_ident::new(0);
+                    representation.write_to_cache(bits, new_value);
+                    self.try_write_raw(representation.get(), mask)#await_
+                        .map_err(bitaccess::WriteError::Backend)
+                }
+            }
+        });
+        let modify = matches!(
+            self.top_level_arguments.implementation,
+            Implementation::GlobalReadWrite(_)
+        )
+        .then(|| {
+            let modify = self.rmw_guard(quote! {
+                let mut representation = super::#representation_ident::new(self.try_read_raw()#await_?);
+                f(&mut representation);
+                self.try_set(representation.get())#await_
+            });
+            quote! {
+                /// Reads the register once, applies all writes done by `f` and stores the result.
+                #vis #asyncness fn try_modify(
+                    &mut self,
+                    f: impl FnOnce(&mut super::#representation_ident),
+                ) -> Result<(), #error> {
+                    #modify
+                }
+
+                #vis #asyncness fn modify(&mut self, f: impl FnOnce(&mut super::#representation_ident)) {
+                    if let Err(error) = self.try_modify(f)#await_ {
+                        panic!("failed to modify register: {:?}", error)
+                    }
+                }
+            }
+        });
+
+        quote! {
+            #read
+            #write
+            #modify
+        }
+    }
+
+    /// `verify_write` and `write_verified` of readable and writable global registers.
+    fn verify_fns(&self, representation_ident: &Ident) -> Option<TokenStream2> {
+        if !matches!(
+            self.top_level_arguments.implementation,
+            Implementation::GlobalReadWrite(_)
+        ) {
+            return None;
+        }
+        let ident = &self.struct_identifier;
+        let base_type = &self.top_level_arguments.base_type;
+        let vis = &self.struct_visibility;
+        let asyncness = self.asyncness();
+        let await_ = self.await_();
+        let receiver = self.receiver();
+        let const_args = self
+            .top_level_arguments
+            .bus
+            .as_ref()
+            .map(|_| quote! { <()> });
+        let write_raw_ident = self.write_raw_ident();
+        let verified_write_raw = self.verify_hook(quote! { new_value }, quote! { mask }).map(
+            |verify| {
+                quote! {
+                    #asyncness fn write_raw(&mut self, new_value: #base_type, mask: #base_type) {
+                        self.write_raw_unverified(new_value, mask)#await_;
+                        #verify
+                    }
+                }
+            },
+        );
+        let compared: Vec<_> = self
+            .fields
+            .iter()
+            .filter(|field| !field.volatile)
+            .map(BitField::mask)
+            .collect();
+
+        Some(quote! {
+            /// Reads the register back and compares it with `expected` on bits of `mask`.
+            /// Only bits of fields not marked `#[volatile]` are compared.
+            #vis #asyncness fn verify_write(
+                #receiver,
+                expected: #base_type,
+                mask: #base_type,
+            ) -> Result<(), bitaccess::WriteMismatch<#base_type>> {
+                let mask: #base_type = mask & (0 #(| #compared)*);
+                let actual = self.read_raw()#await_;
+                if (actual ^ expected) & mask == 0 {
+                    Ok(())
+                } else {
+                    Err(bitaccess::WriteMismatch::new(
+                        expected & mask,
+                        actual & mask,
+                        <super::#ident #const_args>::FIELDS,
+                    ))
                 }
             }
+
+            /// Writes a field and reads the register back, reporting fields which didn't keep
+            /// the written value.
+            #vis #asyncness fn write_verified<F: bitaccess::FieldAccess<#base_type>>(
+                &mut self,
+                bits: bitaccess::FieldDefinition<#base_type, F>,
+                new_value: impl Into<bitaccess::Field<#base_type, F>>,
+            ) -> Result<(), bitaccess::WriteMismatch<#base_type>> {
+                let mask = bits.mask();
+                let mut representation = super::#representation_ident::new(0);
+                representation.write_to_cache(bits, new_value);
+                let expected = representation.get();
+                self.#write_raw_ident(expected, mask)#await_;
+                self.verify_write(expected, mask)#await_
+            }
+
+            #verified_write_raw
         })
     }
+
+    /// Registers with `verify_writes = true` wrap `write_raw` with a read back.
+    fn write_raw_ident(&self) -> Ident {
+        if self.top_level_arguments.verify_writes {
+            Ident::new("write_raw_unverified", self.struct_identifier.span())
+        } else {
+            Ident::new("write_raw", self.struct_identifier.span())
+        }
+    }
+
+    /// `wait_until` and `wait_while` of readable global registers. Synchronous registers
+    /// additionally get `_async` variants, which yield to the executor between polls.
+    fn wait_fns(&self, representation_ident: &Ident) -> Option<TokenStream2> {
+        if !matches!(
+            self.top_level_arguments.implementation,
+            Implementation::GlobalReadOnly(_) | Implementation::GlobalReadWrite(_)
+        ) {
+            return None;
+        }
+        let span = self.struct_identifier.span();
+        // Asynchronous registers have to be awaited anyway, so they always yield
+        let variants: &[(&str, bool)] = if self.top_level_arguments.is_async() {
+            &[("", true)]
+        } else {
+            &[("", false), ("_async", true)]
+        };
+
+        let mut wait_fns = Vec::new();
+        for (suffix, yielding) in variants {
+            for (name, done) in [("wait_until", quote! { == }), ("wait_while", quote! { != })] {
+                wait_fns.push(self.wait_fn(
+                    Ident::new(&format!("{}{}", name, suffix), span),
+                    done,
+                    *yielding,
+                    representation_ident,
+                ));
+            }
+        }
+
+        Some(quote! { #(#wait_fns)* })
+    }
+
+    /// Per-call ordered accessors of synchronous global registers, fenced regardless of `ordering`.
+    fn fenced_fns(&self) -> Option<TokenStream2> {
+        if matches!(
+            self.top_level_arguments.implementation,
+            Implementation::Inline(_)
+        ) || self.top_level_arguments.is_async()
+        {
+            return None;
+        }
+        let base_type = &self.top_level_arguments.base_type;
+        let vis = &self.struct_visibility;
+
+        let read_fenced = self.top_level_arguments.is_read().then(|| {
+            quote! {
+                /// `read` followed by a memory fence.
+                #vis fn read_fenced<F: bitaccess::FieldAccess<#base_type>>(
+                    &self,
+                    bits: bitaccess::FieldDefinition<#base_type, F>,
+                ) -> bitaccess::Field<#base_type, F> {
+                    let field = bitaccess::ReadBits::read(self, bits);
+                    core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+                    field
+                }
+            }
+        });
+        let write_fenced = self.top_level_arguments.is_write().then(|| {
+            quote! {
+                /// `write` preceded by a memory fence.
+                #vis fn write_fenced<F: bitaccess::FieldAccess<#base_type>>(
+                    &mut self,
+                    bits: bitaccess::FieldDefinition<#base_type, F>,
+                    new_value: impl Into<bitaccess::Field<#base_type, F>>,
+                ) {
+                    core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+                    bitaccess::WriteBits::write(self, bits, new_value)
+                }
+            }
+        });
+
+        Some(quote! {
+            #read_fenced
+            #write_fenced
+        })
+    }
+
+    /// Polls the register until `last & mask #done expected`, at most `max_iterations` times.
+    fn wait_fn(
+        &self,
+        name: Ident,
+        done: TokenStream2,
+        yielding: bool,
+        representation_ident: &Ident,
+    ) -> TokenStream2 {
+        let base_type = &self.top_level_arguments.base_type;
+        let vis = &self.struct_visibility;
+        let await_ = self.await_();
+        let receiver = self.receiver();
+        let (asyncness, pause) = if yielding {
+            (
+                Some(quote! { async }),
+                quote! { bitaccess::yield_now().await; },
+            )
+        } else {
+            (None, quote! { core::hint::spin_loop(); })
+        };
+
+        quote! {
+            #vis #asyncness fn #name<F: bitaccess::FieldAccess<#base_type>>(
+                #receiver,
+                bits: bitaccess::FieldDefinition<#base_type, F>,
+                value: impl Into<bitaccess::Field<#base_type, F>>,
+                max_iterations: usize,
+            ) -> Result<(), bitaccess::Timeout<#base_type>> {
+                let mask = bits.mask();
+                let mut expected = super::#representation_ident::new(0);
+                expected.write_to_cache(bits, value);
+                let expected = expected.get();
+
+                let mut iterations = 0;
+                loop {
+                    let last = self.read_raw()#await_;
+                    if last & mask #done expected {
+                        return Ok(());
+                    }
+                    iterations += 1;
+                    if iterations >= max_iterations {
+                        return Err(bitaccess::Timeout { last });
+                    }
+                    #pause
+                }
+            }
+        }
+    }
+
+    /// `validate` checking written values against their fields, used by `try_write`.
+    fn validate_fn(&self) -> Option<TokenStream2> {
+        if !self.top_level_arguments.is_write() {
+            return None;
+        }
+        let base_type = &self.top_level_arguments.base_type;
+        let matchers = self.field_matchers();
+        let checks: Vec<_> = self
+            .fields
+            .iter()
+            .map(|field| {
+                let width_check = field.width_check();
+                let fixed_check = field
+                    .fixed_check()
+                    .map(|fixed_check| quote! { (#fixed_check)?; });
+                let alignment_check = field
+                    .alignment_check()
+                    .map(|alignment_check| quote! { (#alignment_check)?; });
+                let range_check = field
+                    .range_check()
+                    .map(|range_check| quote! { (#range_check)?; });
+                quote! {
+                    {
+                        #fixed_check
+                        #alignment_check
+                        (#width_check)?;
+                        #range_check
+                    }
+                }
+            })
+            .collect();
+
+        Some(quote! {
+            #[allow(unreachable_code)]
+            fn validate<F: bitaccess::FieldAccess<#base_type>>(
+                bits: &bitaccess::FieldDefinition<#base_type, F>,
+                new_value: &bitaccess::Field<#base_type, F>,
+            ) -> Result<(), bitaccess::InvalidValue<#base_type>> {
+                let raw = new_value.value();
+                match bits.mask() {
+                    #(_ if #matchers => #checks,)*
+                    _ => panic!("use provided consts to write to register"),
+                }
+                Ok(())
+            }
+        })
+    }
+
+    /// `try_write` rejecting values which don't fit in the field. Registers with `error` get
+    /// theirs among fallible accessors.
+    fn try_write_fn(&self) -> Option<TokenStream2> {
+        if !self.top_level_arguments.is_write() || self.top_level_arguments.error.is_some() {
+            return None;
+        }
+        let base_type = &self.top_level_arguments.base_type;
+        let vis = &self.struct_visibility;
+        let asyncness = self.asyncness();
+        let await_ = self.await_();
+        let (receiver, write_bits) = if self.top_level_arguments.is_atomic() {
+            (quote! { &self }, quote! { bitaccess::AtomicWriteBits })
+        } else if self.top_level_arguments.is_async() {
+            (quote! { &mut self }, quote! { bitaccess::AsyncWriteBits })
+        } else {
+            (quote! { &mut self }, quote! { bitaccess::WriteBits })
+        };
+
+        Some(quote! {
+            /// Writes the field, rejecting values which don't fit in it instead of truncating them.
+            #vis #asyncness fn try_write<F: bitaccess::FieldAccess<#base_type>>(
+                #receiver,
+                bits: bitaccess::FieldDefinition<#base_type, F>,
+                new_value: impl Into<bitaccess::Field<#base_type, F>>,
+            ) -> Result<(), bitaccess::InvalidValue<#base_type>> {
+                let new_value: bitaccess::Field<#base_type, F> = new_value.into();
+                Self::validate(&bits, &new_value)?;
+                #write_bits::write(self, bits, new_value)#await_;
+                Ok(())
+            }
+        })
+    }
+
+    /// Read back after writes of `verify_writes = true` registers.
+    fn verify_hook(&self, expected: TokenStream2, mask: TokenStream2) -> Option<TokenStream2> {
+        let await_ = self.await_();
+
+        self.top_level_arguments.verify_writes.then(|| {
+            quote! {
+                if let Err(mismatch) = self.verify_write(#expected, #mask)#await_ {
+                    panic!("register doesn't hold written value: {:?}", mismatch)
+                }
+            }
+        })
+    }
+
+    fn global_reader(&self, read_via: &Via) -> TokenStream2 {
+        let reader = read_via.reader(self.is_indexed(), self.top_level_arguments.error.is_some());
+        let fence = self.top_level_arguments.ordering.fence();
+        let after_read = self
+            .top_level_arguments
+            .endian
+            .map(|endian| endian.after_read(&self.top_level_arguments.base_type));
+
+        quote! {
+            #reader
+            #fence
+            #after_read
+        }
+    }
+
+    fn global_writer(&self, write_via: &Via) -> TokenStream2 {
+        let writer = write_via.writer(self.is_indexed(), self.top_level_arguments.error.is_some());
+        let fence = self.top_level_arguments.ordering.fence();
+        let before_write = self
+            .top_level_arguments
+            .endian
+            .map(|endian| endian.before_write());
+
+        quote! {
+            #before_write
+            #fence
+            #writer
+        }
+    }
+
+    fn rmw_guard(&self, read_modify_write: TokenStream2) -> TokenStream2 {
+        match &self.top_level_arguments.rmw {
+            Some(Rmw::CriticalSection) => quote! {
+                bitaccess::critical_section::with(|_| { #read_modify_write })
+            },
+            Some(Rmw::Custom(path)) => quote! {
+                #path(|| { #read_modify_write })
+            },
+            None => read_modify_write,
+        }
+    }
+
+    fn atomic_write_raw_fn(&self) -> Option<TokenStream2> {
+        if !self.top_level_arguments.is_write() {
+            return None;
+        }
+        let base_type = &self.top_level_arguments.base_type;
+        let vis = &self.struct_visibility;
+
+        Some(quote! {
+            fn write_raw(&self, new_value: #base_type, mask: #base_type) {
+                let value = &self.inner.value;
+                if new_value == mask {
+                    value.fetch_or(mask, core::sync::atomic::Ordering::AcqRel);
+                } else if new_value == 0 {
+                    value.fetch_and(!mask, core::sync::atomic::Ordering::AcqRel);
+                } else {
+                    let _ = value.fetch_update(
+                        core::sync::atomic::Ordering::AcqRel,
+                        core::sync::atomic::Ordering::Acquire,
+                        |old_value| Some((old_value & !mask) | new_value),
+                    );
+                }
+            }
+
+            #vis fn set(&self, value: #base_type) {
+                self.inner.value.store(value, core::sync::atomic::Ordering::Release);
+            }
+        })
+    }
+
+    fn compare_exchange_fields_fn(&self) -> TokenStream2 {
+        let base_type = &self.top_level_arguments.base_type;
+        let representation_ident = self.representation_struct_ident();
+
+        quote! {
+            fn compare_exchange_fields(
+                &self,
+                bits: bitaccess::FieldDefinition<#base_type, F>,
+                current: impl Into<bitaccess::Field<#base_type, F>>,
+                new: impl Into<bitaccess::Field<#base_type, F>>,
+            ) -> Result<bitaccess::Field<#base_type, F>, bitaccess::Field<#base_type, F>> {
+                let mask = bits.mask();
+                let mut expected = super::#representation_ident::new(0);
+                expected.write_to_cache(bitaccess::FieldDefinition::<#base_type, F>::new(mask), current);
+                let expected = expected.get();
+                let mut desired = super::#representation_ident::new(0);
+                desired.write_to_cache(bitaccess::FieldDefinition::<#base_type, F>::new(mask), new);
+                let desired = desired.get();
+
+                let mut old_value = self.inner.value.load(core::sync::atomic::Ordering::Acquire);
+                loop {
+                    if old_value & mask != expected {
+                        return Err(super::#representation_ident::new(old_value)
+                            .read(bitaccess::FieldDefinition::new(mask)));
+                    }
+                    match self.inner.value.compare_exchange_weak(
+                        old_value,
+                        (old_value & !mask) | desired,
+                        core::sync::atomic::Ordering::AcqRel,
+                        core::sync::atomic::Ordering::Acquire,
+                    ) {
+                        Ok(previous) => {
+                            return Ok(super::#representation_ident::new(previous)
+                                .read(bitaccess::FieldDefinition::new(mask)))
+                        }
+                        Err(actual) => old_value = actual,
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use syn::parse_quote;
+
+    use super::BitAccess;
+
+    fn expand(ordering: &str) -> String {
+        let ordering: syn::Ident = syn::parse_str(ordering).unwrap();
+        let bit_access = BitAccess::new(
+            quote! {
+                base_type = u32,
+                kind = read_write,
+                ordering = #ordering,
+                read_via = { value = crate::REGISTER },
+                write_via = { crate::REGISTER = value }
+            },
+            parse_quote! {
+                pub enum Doorbell {
+                    #[bits(0..8)]
+                    Channel,
+                }
+            },
+        )
+        .unwrap();
+
+        bit_access.into_token_stream().to_string()
+    }
+
+    #[test]
+    fn fenced_ordering_fences_reads_and_writes() {
+        let expansion = expand("fenced");
+
+        assert!(expansion.contains(
+            &quote! {
+                { value = crate::REGISTER };
+                core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+            }
+            .to_string()
+        ));
+        assert!(expansion.contains(
+            &quote! {
+                core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+                { crate::REGISTER = value };
+            }
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn compiler_ordering_emits_compiler_fences() {
+        let expansion = expand("compiler");
+
+        assert!(expansion.contains(
+            &quote! {
+                { value = crate::REGISTER };
+                core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+            }
+            .to_string()
+        ));
+        assert!(expansion.contains(
+            &quote! {
+                core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+                { crate::REGISTER = value };
+            }
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn relaxed_ordering_emits_no_fences() {
+        let expansion = expand("relaxed");
+
+        assert!(expansion.contains(&quote! { { value = crate::REGISTER }; }.to_string()));
+        assert!(!expansion.contains(
+            &quote! {
+                { value = crate::REGISTER };
+                core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+            }
+            .to_string()
+        ));
+        assert!(!expansion.contains(
+            &quote! {
+                core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+                { crate::REGISTER = value };
+            }
+            .to_string()
+        ));
+        assert!(!expansion.contains("compiler_fence"));
+    }
 }
@@ -1,9 +1,11 @@
-use proc_macro2::{Ident, TokenStream as TokenStream2};
+use proc_macro2::{Ident, Literal, Span, TokenStream as TokenStream2};
 use quote::quote;
 use syn::{
+    parse2,
     parse_quote,
-    parse_quote::parse,
     punctuated::Punctuated,
+    Error,
+    LitInt,
     Token,
     Type,
     Variant,
@@ -11,14 +13,21 @@ use syn::{
 };
 
 use crate::{
+    common,
     extra_enum_access::{ExtraEnumAccess, InlineEnumAccess, InlineEnumEntry},
-    field_level_macro_arguments::FieldLevelMacroArguments,
+    field_level_macro_arguments::{ArrayArg, Encoding, FieldLevelMacroArguments, Fixed, Position},
 };
 
 pub struct BitField {
     field_level_arguments: FieldLevelMacroArguments,
     extra_enum_access: Option<ExtraEnumAccess>,
     pub ident: Ident,
+    /// Field changed by hardware, skipped when verifying writes.
+    pub volatile: bool,
+    /// Position of a single-bit field in `iter_set`, lower goes first, with span of the attribute.
+    pub priority: Option<(u64, Span)>,
+    /// Native type of the field from `#[ty(...)]`.
+    native_type: Option<Type>,
 }
 
 impl BitField {
@@ -29,6 +38,9 @@ impl BitField {
     fn single(variant: Variant) -> syn::Result<Self> {
         let mut bit_attribute = None;
         let mut variant_attribute = None;
+        let mut volatile = false;
+        let mut priority = None;
+        let mut ty_attribute = None;
 
         for attr in variant.attrs {
             if attr.path.is_ident("bitaccess")
@@ -50,6 +62,25 @@ impl BitField {
                 } else {
                     variant_attribute = Some(attr);
                 }
+            } else if attr.path.is_ident("volatile") {
+                volatile = true;
+            } else if attr.path.is_ident("ty") {
+                if ty_attribute.is_some() {
+                    proc_macro_error::abort_call_site!("duplicate ty attribute on a field");
+                } else {
+                    ty_attribute = Some(attr);
+                }
+            } else if attr.path.is_ident("priority") {
+                let lit: LitInt = attr.parse_args()?;
+                if priority
+                    .replace((lit.base10_parse::<u64>()?, lit.span()))
+                    .is_some()
+                {
+                    return Err(Error::new(
+                        lit.span(),
+                        "duplicate priority attribute on a field",
+                    ));
+                }
             }
         }
 
@@ -62,42 +93,545 @@ impl BitField {
             )
         };
 
+        let field_level_arguments = parse2::<FieldLevelMacroArguments>(bit_attribute.tokens)?;
+        if let Some(Some(signed)) = &field_level_arguments.signed {
+            if common::bit_width(signed)
+                .is_some_and(|width| u64::from(width) < field_level_arguments.size)
+            {
+                return Err(Error::new_spanned(
+                    signed,
+                    format!("signed type is narrower than field `{}`", variant.ident),
+                ));
+            }
+        }
+        if field_level_arguments.signed.is_some() && variant_attribute.is_some() {
+            proc_macro_error::abort_call_site!(
+                "signed field `{}` can't have variants",
+                &variant.ident
+            )
+        }
+        if (field_level_arguments.shift.is_some() || field_level_arguments.encoding.is_some())
+            && variant_attribute.is_some()
+        {
+            proc_macro_error::abort_call_site!(
+                "shifted or encoded field `{}` can't have variants",
+                &variant.ident
+            )
+        }
+        if field_level_arguments.fixed.is_some()
+            && (variant_attribute.is_some() || ty_attribute.is_some())
+        {
+            proc_macro_error::abort_call_site!(
+                "fixed-point field `{}` can't have variants or ty attribute",
+                &variant.ident
+            )
+        }
+        if let Some((_, span)) = priority {
+            if field_level_arguments.size != 1 || field_level_arguments.array.is_some() {
+                return Err(Error::new(
+                    span,
+                    format!(
+                        "priority attribute is allowed only on single-bit fields, found on `{}`",
+                        variant.ident
+                    ),
+                ));
+            }
+        }
+
+        let native_type = match ty_attribute {
+            Some(attr) if attr.tokens.is_empty() && field_level_arguments.shift.is_some() => {
+                Some(parse_quote! { usize })
+            }
+            Some(attr) if attr.tokens.is_empty() => Some(match field_level_arguments.size {
+                1 => parse_quote! { bool },
+                2..=8 => parse_quote! { u8 },
+                9..=16 => parse_quote! { u16 },
+                17..=32 => parse_quote! { u32 },
+                33..=64 => parse_quote! { u64 },
+                _ => parse_quote! { u128 },
+            }),
+            Some(attr) => Some(attr.parse_args::<Type>()?),
+            None => None,
+        };
+        // Values read from the field, shifted ones included, have to fit in the native type
+        if let Some(native_type) = &native_type {
+            let width = field_level_arguments.size + field_level_arguments.shift.unwrap_or(0);
+            if native_width(native_type).is_some_and(|native| u64::from(native) < width) {
+                return Err(Error::new_spanned(
+                    native_type,
+                    format!("ty is narrower than field `{}`", variant.ident),
+                ));
+            }
+        }
+        if native_type.is_some()
+            && (variant_attribute.is_some() || field_level_arguments.signed.is_some())
+        {
+            proc_macro_error::abort_call_site!(
+                "field `{}` with ty attribute can't be signed or have variants",
+                &variant.ident
+            )
+        }
+
         Ok(Self {
-            field_level_arguments: parse::<FieldLevelMacroArguments>(bit_attribute.tokens),
+            field_level_arguments,
             extra_enum_access: variant_attribute
                 .map(|i| i.parse_args_with(<ExtraEnumAccess as syn::parse::Parse>::parse))
                 .transpose()?,
             ident: variant.ident,
+            volatile,
+            priority,
+            native_type,
         })
     }
 
     pub fn reader(&self) -> TokenStream2 {
-        let Self {
-            field_level_arguments: FieldLevelMacroArguments { offset, .. },
-            ..
-        } = self;
-        quote! {
-            (value & bits.mask()) >> #offset
+        let read = if let Some(pieces) = &self.field_level_arguments.split {
+            let mut shift = 0;
+            let parts: Vec<_> = pieces
+                .iter()
+                .map(|Position { lo, len }| {
+                    let part = quote! { (((value >> #lo) & ((1 << #len) - 1)) << #shift) };
+                    shift += len;
+                    part
+                })
+                .collect();
+            quote! { #(#parts)|* }
+        } else {
+            let offset = self.offset();
+            quote! {
+                (value & bits.mask()) >> #offset
+            }
+        };
+
+        if self.field_level_arguments.signed.is_some() {
+            let sign_extend = self.sign_extend(quote! { raw });
+            quote! {
+                {
+                    let raw = #read;
+                    #sign_extend
+                }
+            }
+        } else if let Some(shift) = self.field_level_arguments.shift {
+            quote! { ((#read) << #shift) }
+        } else if let Some(encoding) = self.field_level_arguments.encoding {
+            let decode = Self::decode(encoding);
+            quote! {
+                {
+                    let raw = #read;
+                    #decode
+                }
+            }
+        } else {
+            read
         }
     }
 
     pub fn writer(&self) -> TokenStream2 {
-        let Self {
-            field_level_arguments: FieldLevelMacroArguments { offset, .. },
-            ..
-        } = self;
+        let value = match &self.field_level_arguments {
+            FieldLevelMacroArguments {
+                shift: Some(shift), ..
+            } => quote! { (new_value.value() >> #shift) },
+            FieldLevelMacroArguments {
+                encoding: Some(encoding),
+                ..
+            } => {
+                let encode = Self::encode(*encoding);
+                quote! { { let raw = new_value.value(); #encode } }
+            }
+            _ => quote! { new_value.value() },
+        };
+        let write = if let Some(pieces) = &self.field_level_arguments.split {
+            let mut shift = 0;
+            let parts: Vec<_> = pieces
+                .iter()
+                .map(|Position { lo, len }| {
+                    let part = quote! { (((#value >> #shift) & ((1 << #len) - 1)) << #lo) };
+                    shift += len;
+                    part
+                })
+                .collect();
+            quote! {
+                self.write_raw(#(#parts)|*, bits.mask())
+            }
+        } else {
+            let offset = self.offset();
+            quote! {
+                self.write_raw((#value & (bits.mask() >> #offset)) << #offset, bits.mask())
+            }
+        };
+
+        let check = self.width_check();
+        if self.field_level_arguments.signed.is_some() {
+            let ident = &self.ident;
+            quote! {
+                {
+                    let raw = new_value.value();
+                    if (#check).is_err() {
+                        panic!("value out of range of signed field {}", stringify!(#ident));
+                    }
+                    #write
+                }
+            }
+        } else {
+            let ident = &self.ident;
+            let range_guard = self.range_check().map(|range_check| {
+                quote! {
+                    if (#range_check).is_err() {
+                        panic!("value out of range of field {}", stringify!(#ident));
+                    }
+                }
+            });
+            let fixed_guard = self.fixed_check().map(|fixed_check| {
+                quote! {
+                    if (#fixed_check).is_err() {
+                        panic!("value out of range of fixed-point field {}", stringify!(#ident));
+                    }
+                }
+            });
+            let alignment_guard = self.alignment_check().map(|alignment_check| {
+                quote! {
+                    if (#alignment_check).is_err() {
+                        panic!("misaligned value written to field {}", stringify!(#ident));
+                    }
+                }
+            });
+            // Too wide BCD values have no encoding, so they're always rejected
+            let width_guard = if self.field_level_arguments.encoding == Some(Encoding::Bcd) {
+                quote! {
+                    if (#check).is_err() {
+                        panic!("value out of range of BCD field {}", stringify!(#ident));
+                    }
+                }
+            } else {
+                quote! {
+                    if bitaccess::CHECKED && cfg!(debug_assertions) {
+                        if let Err(too_wide) = #check {
+                            panic!("{:?}", too_wide);
+                        }
+                    }
+                }
+            };
+            quote! {
+                {
+                    let raw = new_value.value();
+                    #fixed_guard
+                    // Conversion into the field already lost the value, eg. raw value of `UInt`
+                    if new_value.overflowed() {
+                        if let Err(too_wide) = #check {
+                            panic!("{:?}", too_wide);
+                        }
+                    }
+                    #alignment_guard
+                    #range_guard
+                    #width_guard
+                    #write
+                }
+            }
+        }
+    }
+
+    /// Checks whether `raw` lies in `range` declared for the field, returning
+    /// `Result<(), bitaccess::ValueOutOfRange>`.
+    pub fn range_check(&self) -> Option<TokenStream2> {
+        let name = self.ident.to_string();
+
+        self.field_level_arguments.range.map(|(min, max)| {
+            let (min, max) = (Literal::u64_unsuffixed(min), Literal::u64_unsuffixed(max));
+            quote! {
+                if !(#min..=#max).contains(&raw) {
+                    Err(bitaccess::ValueOutOfRange { field: #name, value: raw, min: #min, max: #max })
+                } else {
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// Checks whether float written to fixed-point field was in range of its format, returning
+    /// `Result<(), bitaccess::ValueOutOfRange>` with raw bits of the saturated value and of
+    /// format's `MIN` and `MAX`.
+    pub fn fixed_check(&self) -> Option<TokenStream2> {
+        let name = self.ident.to_string();
+        let size = self.field_level_arguments.size as u32;
+
+        self.field_level_arguments.fixed.map(|Fixed { signed, .. }| {
+            let (min, max) = if signed {
+                (1u128 << (size - 1), (1u128 << (size - 1)) - 1)
+            } else {
+                (0, u128::MAX >> (u128::BITS - size))
+            };
+            let (min, max) = (Literal::u128_unsuffixed(min), Literal::u128_unsuffixed(max));
+            quote! {
+                if new_value.overflowed() {
+                    Err(bitaccess::ValueOutOfRange { field: #name, value: raw, min: #min, max: #max })
+                } else {
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// Checks whether the field of register `value` lies in its `range`, for every element of
+    /// field arrays. Propagates `bitaccess::ValueOutOfRange` with `?`.
+    pub fn stored_range_check(&self, register: &Ident) -> Option<TokenStream2> {
+        let ident = &self.ident;
+        let range_check = self.range_check()?;
+        let reader = self.reader();
+        let check = quote! {
+            let raw = #reader;
+            (#range_check)?;
+        };
+
+        Some(if self.field_level_arguments.array.is_some() {
+            quote! {
+                for index in 0..super::#register::#ident.len() {
+                    let bits = super::#register::#ident.at(index);
+                    #check
+                }
+            }
+        } else {
+            quote! {
+                {
+                    let bits = super::#register::#ident;
+                    #check
+                }
+            }
+        })
+    }
+
+    /// Checks whether `raw` has no bits below `shift` set, returning
+    /// `Result<(), bitaccess::ValueMisaligned>`.
+    pub fn alignment_check(&self) -> Option<TokenStream2> {
+        let name = self.ident.to_string();
+
+        self.field_level_arguments.shift.map(|shift| {
+            quote! {
+                if raw & ((1 << #shift) - 1) != 0 {
+                    Err(bitaccess::ValueMisaligned { field: #name, value: raw, align: 1 << #shift })
+                } else {
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// Checks whether `raw` fits in the field, returning `Result<(), bitaccess::ValueTooWide>`.
+    pub fn width_check(&self) -> TokenStream2 {
+        let name = self.ident.to_string();
+        let size = self.field_level_arguments.size;
+
+        // Sign-extended value of signed fields has to survive truncation to field size,
+        // and the written value has to survive conversion to `base_type` before that
+        if self.field_level_arguments.signed.is_some() {
+            let sign = size - 1;
+            let sign_extend = self.sign_extend(quote! { (raw & ((1 << #size) - 1)) });
+            quote! {
+                if new_value.overflowed() || #sign_extend != raw {
+                    Err(bitaccess::ValueTooWide { field: #name, value: raw, max: (1 << #sign) - 1 })
+                } else {
+                    Ok(())
+                }
+            }
+        } else if self.field_level_arguments.encoding == Some(Encoding::Bcd) {
+            let max = Literal::u128_unsuffixed(10u128.pow(size as u32 / 4) - 1);
+            quote! {
+                if new_value.overflowed() || raw > #max {
+                    Err(bitaccess::ValueTooWide { field: #name, value: raw, max: #max })
+                } else {
+                    Ok(())
+                }
+            }
+        } else {
+            let shift = self.field_level_arguments.shift.unwrap_or(0);
+            quote! {
+                if new_value.overflowed() || raw > ((1 << #size) - 1) << #shift {
+                    Err(bitaccess::ValueTooWide { field: #name, value: raw, max: ((1 << #size) - 1) << #shift })
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Encodes `raw` value of the field as stored in the register.
+    fn encode(encoding: Encoding) -> TokenStream2 {
+        match encoding {
+            Encoding::Bcd => quote! {
+                {
+                    let mut decimal = raw;
+                    let mut encoded = 0;
+                    let mut digit = 0;
+                    while decimal != 0 {
+                        encoded |= (decimal % 10) << digit;
+                        decimal /= 10;
+                        digit += 4;
+                    }
+                    encoded
+                }
+            },
+            Encoding::Gray => quote! { (raw ^ (raw >> 1)) },
+        }
+    }
+
+    /// Decodes `raw` value stored in the register. Invalid BCD digits, above 9, are decoded
+    /// arithmetically, eg. `0xff` becomes 165.
+    fn decode(encoding: Encoding) -> TokenStream2 {
+        match encoding {
+            Encoding::Bcd => quote! {
+                {
+                    let mut digits = raw;
+                    let mut decoded = 0;
+                    let mut scale = 1;
+                    while digits != 0 {
+                        decoded += (digits & 0xf) * scale;
+                        digits >>= 4;
+                        scale *= 10;
+                    }
+                    decoded
+                }
+            },
+            Encoding::Gray => quote! {
+                {
+                    let mut gray = raw;
+                    let mut decoded = raw;
+                    while gray != 0 {
+                        gray >>= 1;
+                        decoded ^= gray;
+                    }
+                    decoded
+                }
+            },
+        }
+    }
+
+    /// Copies sign bit of field-sized `raw` into all higher bits.
+    fn sign_extend(&self, raw: TokenStream2) -> TokenStream2 {
+        let size = self.field_level_arguments.size;
+        let sign = size - 1;
+
         quote! {
-            self.write_raw((new_value.value() & (bits.mask() >> #offset)) << #offset, bits.mask())
+            if #raw & (1 << #sign) != 0 {
+                #raw | !((1 << #size) - 1)
+            } else {
+                #raw
+            }
         }
     }
 
+    /// Type of signed fields, explicit or the narrowest holding the field.
+    fn signed_type(&self) -> Option<Type> {
+        let FieldLevelMacroArguments { size, signed, .. } = &self.field_level_arguments;
+
+        signed.as_ref().map(|signed| match signed {
+            Some(typ) => typ.clone(),
+            None => match size {
+                0..=8 => parse_quote! { i8 },
+                9..=16 => parse_quote! { i16 },
+                17..=32 => parse_quote! { i32 },
+                33..=64 => parse_quote! { i64 },
+                _ => parse_quote! { i128 },
+            },
+        })
+    }
+
+    /// Offset of the field accessed by `bits`. Elements of field arrays differ only by offset,
+    /// so it's taken from their mask.
+    fn offset(&self) -> TokenStream2 {
+        match &self.field_level_arguments {
+            FieldLevelMacroArguments { array: Some(_), .. } => {
+                quote! { bits.mask().trailing_zeros() }
+            }
+            FieldLevelMacroArguments { offset, .. } => quote! { #offset },
+        }
+    }
+
+    /// Condition selecting this field in accessors matching on `bits`.
+    pub fn matcher(&self, register: &Ident) -> TokenStream2 {
+        let ident = &self.ident;
+        if self.field_level_arguments.array.is_some() {
+            quote! { super::#register::#ident.contains(bits.mask()) }
+        } else {
+            quote! { super::#register::#ident.mask() == bits.mask() }
+        }
+    }
+
+    /// Masks of all elements of a field array, all pieces of a split field, or of the field itself.
+    fn element_masks(&self) -> Vec<TokenStream2> {
+        let FieldLevelMacroArguments {
+            offset,
+            size,
+            array,
+            split,
+            ..
+        } = &self.field_level_arguments;
+
+        if let Some(pieces) = split {
+            return pieces
+                .iter()
+                .map(|Position { lo, len }| quote! { ((1 << #len) - 1) << #lo })
+                .collect();
+        }
+
+        match array {
+            Some(ArrayArg { count, stride }) => (0..*count)
+                .map(|index| {
+                    let offset = offset + index * stride;
+                    quote! { ((1 << #size) - 1) << #offset }
+                })
+                .collect(),
+            None => vec![quote! { ((1 << #size) - 1) << #offset }],
+        }
+    }
+
+    /// Mask of the field, covering all elements of field arrays and pieces of split fields.
+    pub fn mask(&self) -> TokenStream2 {
+        let masks = self.element_masks();
+
+        quote! { #(#masks)|* }
+    }
+
+    /// Bit above the highest piece of a split field.
+    pub fn split_end(&self) -> Option<u64> {
+        let pieces = self.field_level_arguments.split.as_ref()?;
+
+        pieces.iter().map(|Position { lo, len }| lo + len).max()
+    }
+
+    /// Offset of a single-bit field, listed by `iter_set`.
+    pub fn flag_offset(&self) -> Option<u64> {
+        match &self.field_level_arguments {
+            FieldLevelMacroArguments {
+                offset,
+                size: 1,
+                array: None,
+                split: None,
+                ..
+            } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    pub fn field_info(&self) -> TokenStream2 {
+        let name = self.ident.to_string();
+        let mask = self.mask();
+
+        let range = match self.field_level_arguments.range {
+            Some((min, max)) => {
+                let (min, max) = (Literal::u64_unsuffixed(min), Literal::u64_unsuffixed(max));
+                quote! { Some((#min, #max)) }
+            }
+            None => quote! { None },
+        };
+
+        quote! { bitaccess::FieldInfo { name: #name, mask: #mask, range: #range } }
+    }
+
     pub fn const_enum(&self, vis: &Visibility, base_type: &Type) -> TokenStream2 {
         let Self {
-            field_level_arguments: FieldLevelMacroArguments { offset, size },
             ident,
             extra_enum_access,
+            ..
         } = self;
-
         let name = Ident::new(&ident.to_string(), ident.span());
         let field_type = extra_enum_access
             .as_ref()
@@ -108,10 +642,37 @@ impl BitField {
                     parse_quote! { #ident }
                 }
             })
-            .unwrap_or_else(|| base_type.clone());
+            .or_else(|| self.signed_type())
+            .or_else(|| self.native_type.clone())
+            .or_else(|| {
+                self.field_level_arguments
+                    .fixed
+                    .map(|Fixed { signed, int, frac }| {
+                        let (int, frac) = (int as u32, frac as u32);
+                        if signed {
+                            parse_quote! { bitaccess::Q<#int, #frac> }
+                        } else {
+                            parse_quote! { bitaccess::UQ<#int, #frac> }
+                        }
+                    })
+            })
+            .or_else(|| self.field_level_arguments.shift.map(|_| base_type.clone()))
+            .unwrap_or_else(|| {
+                let size = self.field_level_arguments.size as u32;
+                parse_quote! { bitaccess::UInt<#size> }
+            });
 
-        quote! {
-            #vis const #name: bitaccess::FieldDefinition<#base_type, #field_type> = bitaccess::FieldDefinition::new(((1 << #size) - 1) << #offset);
+        if let Some(ArrayArg { count, .. }) = &self.field_level_arguments.array {
+            let count = *count as usize;
+            let masks = self.element_masks();
+            quote! {
+                #vis const #name: bitaccess::FieldArray<#base_type, #field_type, #count> = bitaccess::FieldArray::new([#(#masks),*]);
+            }
+        } else {
+            let mask = self.mask();
+            quote! {
+                #vis const #name: bitaccess::FieldDefinition<#base_type, #field_type> = bitaccess::FieldDefinition::new(#mask);
+            }
         }
     }
 
@@ -186,3 +747,12 @@ impl BitField {
         Ident::new(&self.ident.to_string(), self.ident.span())
     }
 }
+
+/// Width in bits of values of `#[ty(...)]` type, `char` fields hold Latin-1 characters.
+fn native_width(native_type: &Type) -> Option<u32> {
+    match native_type {
+        Type::Path(path) if path.path.is_ident("bool") => Some(1),
+        Type::Path(path) if path.path.is_ident("char") => Some(8),
+        _ => common::bit_width(native_type),
+    }
+}
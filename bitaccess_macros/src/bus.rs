@@ -0,0 +1,104 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_quote, LitInt, Type};
+
+use crate::top_level_macro_arguments::Via;
+
+/// Protocol used to reach registers of an external chip.
+pub enum BusKind {
+    I2c,
+    Spi,
+}
+
+/// Backend for registers of external chips, accessed through `bitaccess::bus::RegisterBus`.
+/// Such registers hold the bus, so their struct is generic over `BUS`.
+pub struct Bus {
+    pub kind: BusKind,
+    /// Device address, used only by I2C.
+    pub device: Option<LitInt>,
+    pub register: LitInt,
+    /// Register address is 16 bit long, declared with `register_width = 16`.
+    pub wide: bool,
+    /// Accessed through `bitaccess::bus::AsyncRegisterBus`, all accessors become `async`.
+    pub asynchronous: bool,
+    /// Accessed with `read_volatile_register` / `write_volatile_register`, bypassing caches.
+    pub volatile: bool,
+}
+
+impl Bus {
+    /// Bound of `BUS` generic parameter.
+    pub fn bound(&self) -> TokenStream2 {
+        let kind = match self.kind {
+            BusKind::I2c => quote! { bitaccess::bus::I2c },
+            BusKind::Spi => quote! { bitaccess::bus::Spi },
+        };
+
+        if self.asynchronous {
+            quote! { bitaccess::bus::AsyncRegisterBus<#kind> }
+        } else {
+            quote! { bitaccess::bus::RegisterBus<#kind> }
+        }
+    }
+
+    pub fn read_via(&self, base_type: &Type) -> Via {
+        let bound = self.bound();
+        let device = self.device();
+        let register = self.register();
+        let (bus, await_) = self.access();
+        let read_register = if self.volatile {
+            quote! { read_volatile_register }
+        } else {
+            quote! { read_register }
+        };
+
+        Via::Statements(parse_quote! {
+            {
+                let mut bytes = [0u8; core::mem::size_of::<#base_type>()];
+                <BUS as #bound>::#read_register(#bus, #device, #register, &mut bytes)#await_?;
+                value = <#base_type>::from_ne_bytes(bytes);
+            }
+        })
+    }
+
+    pub fn write_via(&self) -> Via {
+        let bound = self.bound();
+        let device = self.device();
+        let register = self.register();
+        let (bus, await_) = self.access();
+        let write_register = if self.volatile {
+            quote! { write_volatile_register }
+        } else {
+            quote! { write_register }
+        };
+
+        Via::Statements(parse_quote! {
+            <BUS as #bound>::#write_register(#bus, #device, #register, &value.to_ne_bytes())#await_?
+        })
+    }
+
+    /// Mutable reference to the bus and `.await` for asynchronous registers.
+    /// Those take `&mut self` in all accessors, so they don't need to borrow the bus dynamically.
+    fn access(&self) -> (TokenStream2, Option<TokenStream2>) {
+        if self.asynchronous {
+            (quote! { self.bus.get_mut() }, Some(quote! { .await }))
+        } else {
+            (quote! { &mut *self.bus.borrow_mut() }, None)
+        }
+    }
+
+    fn register(&self) -> TokenStream2 {
+        let register = &self.register;
+        if self.wide {
+            quote! { bitaccess::bus::RegisterAddress::Wide(#register) }
+        } else {
+            quote! { bitaccess::bus::RegisterAddress::Narrow(#register) }
+        }
+    }
+
+    fn device(&self) -> TokenStream2 {
+        match &self.device {
+            Some(device) => quote! { #device },
+            None => quote! { 0 },
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use syn::{parse_quote, Error, Lit, PatRange, RangeLimits};
+use proc_macro2::Ident;
+use syn::{parse_quote, spanned::Spanned, Error, Lit, PatRange, RangeLimits, Type};
 
 use crate::field_level_macro_arguments::Position;
 
-pub fn range_from_pat(input: &PatRange) -> syn::Result<Position> {
+/// Bits or values covered by a range. With `descending`, ranges like `30..25` are accepted and
+/// include both ends, following datasheet notation of split field pieces.
+pub fn range_from_pat(input: &PatRange, descending: bool) -> syn::Result<Position> {
     let PatRange { lo, limits, hi, .. } = input;
 
     let lo: Lit = parse_quote! { #lo };
@@ -11,6 +14,20 @@ pub fn range_from_pat(input: &PatRange) -> syn::Result<Position> {
     let lo = int_from_lit(lo)?;
     let hi = int_from_lit(hi)?;
 
+    if hi < lo {
+        return if descending {
+            Ok(Position {
+                lo: hi,
+                len: lo - hi + 1,
+            })
+        } else {
+            Err(Error::new_spanned(
+                input,
+                "range end is lower than its start",
+            ))
+        };
+    }
+
     match limits {
         RangeLimits::HalfOpen(_) => Ok(Position { lo, len: hi - lo }), // 0 sized bitfields aren't supported anyway
         RangeLimits::Closed(_) => Ok(Position {
@@ -26,3 +43,52 @@ pub fn int_from_lit(lit: Lit) -> syn::Result<u64> {
         _ => Err(Error::new(lit.span(), "invalid value for parameter")),
     }
 }
+
+pub fn atomic_type(base_type: &Type) -> syn::Result<Type> {
+    let atomic = match base_type {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident().and_then(|ident| {
+            Some(match ident.to_string().as_str() {
+                "u8" => "AtomicU8",
+                "u16" => "AtomicU16",
+                "u32" => "AtomicU32",
+                "u64" => "AtomicU64",
+                "usize" => "AtomicUsize",
+                "i8" => "AtomicI8",
+                "i16" => "AtomicI16",
+                "i32" => "AtomicI32",
+                "i64" => "AtomicI64",
+                "isize" => "AtomicIsize",
+                _ => return None,
+            })
+        }),
+        _ => None,
+    };
+
+    match atomic {
+        Some(atomic) => {
+            let atomic = Ident::new(atomic, base_type.span());
+            Ok(parse_quote! { core::sync::atomic::#atomic })
+        }
+        None => Err(Error::new_spanned(
+            base_type,
+            "`storage = atomic` requires primitive integer `base_type`",
+        )),
+    }
+}
+
+/// Width in bits of primitive integer `base_type`.
+pub fn bit_width(base_type: &Type) -> Option<u32> {
+    match base_type {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident().and_then(|ident| {
+            Some(match ident.to_string().as_str() {
+                "u8" | "i8" => 8,
+                "u16" | "i16" => 16,
+                "u32" | "i32" => 32,
+                "u64" | "i64" => 64,
+                "u128" | "i128" => 128,
+                _ => return None,
+            })
+        }),
+        _ => None,
+    }
+}
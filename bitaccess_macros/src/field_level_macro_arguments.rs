@@ -1,12 +1,17 @@
+use proc_macro2::Span;
 use syn::{
     parenthesized,
     parse::{Parse, ParseStream},
     parse_quote,
     punctuated::Punctuated,
+    spanned::Spanned,
     Error,
     Lit,
+    LitStr,
     Pat,
+    PatRange,
     Token,
+    Type,
 };
 
 use crate::common;
@@ -14,19 +19,82 @@ use crate::common;
 mod kw {
     syn::custom_keyword!(offset);
     syn::custom_keyword!(size);
+    syn::custom_keyword!(count);
+    syn::custom_keyword!(stride);
+    syn::custom_keyword!(order);
+    syn::custom_keyword!(msb_first);
+    syn::custom_keyword!(lsb_first);
+    syn::custom_keyword!(signed);
+    syn::custom_keyword!(range);
+    syn::custom_keyword!(shift);
+    syn::custom_keyword!(encoding);
+    syn::custom_keyword!(bcd);
+    syn::custom_keyword!(gray);
+    syn::custom_keyword!(fixed);
 }
 
 pub struct FieldLevelMacroArguments {
     pub offset: u64,
     pub size: u64,
+    pub array: Option<ArrayArg>,
+    /// Pieces of a non-contiguous field, starting from the least significant part of its value.
+    pub split: Option<Vec<Position>>,
+    /// Two's complement field, read into given signed type or one matching its size.
+    pub signed: Option<Option<Type>>,
+    /// Inclusive bounds of values allowed by hardware.
+    pub range: Option<(u64, u64)>,
+    /// Field holds value shifted right by given number of bits, eg. frame number of an address.
+    pub shift: Option<u64>,
+    /// Encoding of the stored value, decoded on read and encoded on write.
+    pub encoding: Option<Encoding>,
+    /// Fixed-point format of the field, eg. `Q1.15`.
+    pub fixed: Option<Fixed>,
+}
+
+/// Field repeated `count` times, each copy `stride` bits after the previous one.
+pub struct ArrayArg {
+    pub count: u64,
+    pub stride: u64,
 }
 
 #[derive(Default)]
 pub struct FieldLevelMacroArgumentsBuilder {
     pub offset: Option<u64>,
     size: Option<u64>,
+    count: Option<u64>,
+    stride: Option<u64>,
+    pieces: Vec<Position>,
+    order: Option<Order>,
+    signed: Option<Option<Type>>,
+    range: Option<(u64, u64)>,
+    shift: Option<u64>,
+    encoding: Option<Encoding>,
+    fixed: Option<Fixed>,
+}
+
+/// Order in which pieces of a split field are listed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Order {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// Encoding of a field value, used by RTC chips (BCD) or encoders (Gray code).
+#[derive(Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Bcd,
+    Gray,
+}
+
+/// Fixed-point format, `Q{int}.{frac}` or `UQ{int}.{frac}`. Integer bits include the sign.
+#[derive(Clone, Copy)]
+pub struct Fixed {
+    pub signed: bool,
+    pub int: u64,
+    pub frac: u64,
 }
 
+#[derive(Clone, Copy)]
 pub struct Position {
     pub lo: u64,
     pub len: u64,
@@ -35,12 +103,75 @@ pub struct Position {
 pub enum FieldArgument {
     Offset(u64),
     Size(u64),
-    Range(Position),
-    Single(u64),
+    Range(PatRange),
+    Single(u64, Span),
+    Count(u64),
+    Stride(u64),
+    Order(Order),
+    Signed(Option<Box<Type>>),
+    ValueRange((u64, u64)),
+    Shift(u64),
+    Encoding(Encoding),
+    Fixed(Fixed),
 }
 
 impl FieldLevelMacroArgumentsBuilder {
-    fn build(self) -> FieldLevelMacroArguments {
+    fn add_piece(&mut self, piece: Position, span: Span) -> syn::Result<()> {
+        let overlaps = self
+            .pieces
+            .iter()
+            .any(|other| piece.lo < other.lo + other.len && other.lo < piece.lo + piece.len);
+        if overlaps {
+            return Err(Error::new(span, "pieces of split field overlap"));
+        }
+        self.pieces.push(piece);
+
+        Ok(())
+    }
+
+    fn build(mut self) -> FieldLevelMacroArguments {
+        let mut split = None;
+        match self.pieces.as_slice() {
+            [] => {}
+            [Position { lo, len }] => {
+                self.offset = Some(*lo);
+                self.size = Some(*len);
+            }
+            [..] => {
+                if self.count.is_some() {
+                    proc_macro_error::abort_call_site!("split fields can't form field arrays")
+                }
+                let mut pieces = self.pieces;
+                if self.order != Some(Order::LsbFirst) {
+                    pieces.reverse();
+                }
+                self.offset = Some(pieces[0].lo);
+                self.size = Some(pieces.iter().map(|piece| piece.len).sum());
+                split = Some(pieces);
+            }
+        }
+        if self.range.is_some() && self.signed.is_some() {
+            proc_macro_error::abort_call_site!("`range` can't be used with signed fields")
+        }
+        if self.shift.is_some() && self.signed.is_some() {
+            proc_macro_error::abort_call_site!("`shift` can't be used with signed fields")
+        }
+        if self.encoding.is_some() && (self.signed.is_some() || self.shift.is_some()) {
+            proc_macro_error::abort_call_site!(
+                "`encoding` can't be used with signed or shifted fields"
+            )
+        }
+        if self.fixed.is_some()
+            && (self.signed.is_some() || self.shift.is_some() || self.encoding.is_some())
+        {
+            proc_macro_error::abort_call_site!(
+                "`fixed` can't be used with signed, shifted or encoded fields"
+            )
+        }
+        if self.order.is_some() && split.is_none() {
+            proc_macro_error::abort_call_site!("`order` applies only to split fields")
+        }
+
         let offset = match self.offset {
             Some(offset) => offset,
             None => {
@@ -53,7 +184,67 @@ impl FieldLevelMacroArgumentsBuilder {
                 proc_macro_error::abort_call_site!("missing `size` entry in bitaccess attribute")
             }
         };
-        FieldLevelMacroArguments { offset, size }
+        if self.encoding == Some(Encoding::Bcd) && size % 4 != 0 {
+            proc_macro_error::abort_call_site!("BCD field size has to be a multiple of 4 bits")
+        }
+        if let Some(Fixed { int, frac, .. }) = self.fixed {
+            if int + frac != size {
+                proc_macro_error::abort_call_site!(
+                    "fixed-point format has {} bits, but the field has {}",
+                    int + frac,
+                    size
+                )
+            }
+        }
+        let array = match (self.count, self.stride) {
+            (Some(count), stride) => {
+                let stride = stride.unwrap_or(size);
+                if count == 0 || stride < size {
+                    proc_macro_error::abort_call_site!(
+                        "field array requires non-zero `count` and `stride` not smaller than field size"
+                    )
+                }
+                Some(ArrayArg { count, stride })
+            }
+            (None, Some(_)) => {
+                proc_macro_error::abort_call_site!(
+                    "`stride` requires `count` in bitaccess attribute"
+                )
+            }
+            (None, None) => None,
+        };
+        FieldLevelMacroArguments {
+            offset,
+            size,
+            array,
+            split,
+            signed: self.signed,
+            range: self.range,
+            shift: self.shift,
+            encoding: self.encoding,
+            fixed: self.fixed,
+        }
+    }
+}
+
+impl Fixed {
+    fn parse(lit: LitStr) -> syn::Result<Self> {
+        let format = lit.value();
+        let (signed, bits) = match format.strip_prefix("UQ") {
+            Some(bits) => (false, bits),
+            None => (true, format.strip_prefix('Q').unwrap_or_default()),
+        };
+        let parsed = bits
+            .split_once('.')
+            .and_then(|(int, frac)| Some((int.parse().ok()?, frac.parse().ok()?)));
+
+        match parsed {
+            Some((int, frac)) if int > 0 || !signed => Ok(Self { signed, int, frac }),
+            _ => Err(Error::new_spanned(
+                lit,
+                "expected fixed-point format like \"Q1.15\" or \"UQ4.4\"",
+            )),
+        }
     }
 }
 
@@ -70,14 +261,78 @@ impl Parse for FieldArgument {
             let _: Token![=] = input.parse()?;
             let lit = common::int_from_lit(input.parse::<Lit>()?)?;
             Ok(Self::Size(lit))
+        } else if lookahead.peek(kw::count) {
+            let _: kw::count = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let lit = common::int_from_lit(input.parse::<Lit>()?)?;
+            Ok(Self::Count(lit))
+        } else if lookahead.peek(kw::stride) {
+            let _: kw::stride = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let lit = common::int_from_lit(input.parse::<Lit>()?)?;
+            Ok(Self::Stride(lit))
+        } else if lookahead.peek(kw::signed) {
+            let _: kw::signed = input.parse()?;
+            if input.peek(Token![=]) {
+                let _: Token![=] = input.parse()?;
+                Ok(Self::Signed(Some(Box::new(input.parse()?))))
+            } else {
+                Ok(Self::Signed(None))
+            }
+        } else if lookahead.peek(kw::range) {
+            let _: kw::range = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            match input.parse::<Pat>()? {
+                Pat::Range(range) => match common::range_from_pat(&range, false)? {
+                    Position { len: 0, .. } => Err(Error::new_spanned(range, "`range` is empty")),
+                    Position { lo, len } => Ok(Self::ValueRange((lo, lo + len - 1))),
+                },
+                pat => Err(Error::new_spanned(pat, "`range` expects a range of values")),
+            }
+        } else if lookahead.peek(kw::shift) {
+            let _: kw::shift = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let lit = common::int_from_lit(input.parse::<Lit>()?)?;
+            Ok(Self::Shift(lit))
+        } else if lookahead.peek(kw::encoding) {
+            let _: kw::encoding = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let lookahead = input.lookahead1();
+            if lookahead.peek(kw::bcd) {
+                let _: kw::bcd = input.parse()?;
+                Ok(Self::Encoding(Encoding::Bcd))
+            } else if lookahead.peek(kw::gray) {
+                let _: kw::gray = input.parse()?;
+                Ok(Self::Encoding(Encoding::Gray))
+            } else {
+                Err(lookahead.error())
+            }
+        } else if lookahead.peek(kw::fixed) {
+            let _: kw::fixed = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            Ok(Self::Fixed(Fixed::parse(input.parse()?)?))
+        } else if lookahead.peek(kw::order) {
+            let _: kw::order = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let lookahead = input.lookahead1();
+            if lookahead.peek(kw::msb_first) {
+                let _: kw::msb_first = input.parse()?;
+                Ok(Self::Order(Order::MsbFirst))
+            } else if lookahead.peek(kw::lsb_first) {
+                let _: kw::lsb_first = input.parse()?;
+                Ok(Self::Order(Order::LsbFirst))
+            } else {
+                Err(lookahead.error())
+            }
         } else {
             if let Ok(pat) = input.parse::<Pat>() {
                 match pat {
-                    Pat::Range(range) => return Ok(Self::Range(common::range_from_pat(&range)?)),
+                    Pat::Range(range) => return Ok(Self::Range(range)),
                     Pat::Lit(lit) => {
                         let lit: Lit = parse_quote! { #lit };
+                        let span = lit.span();
                         let single = common::int_from_lit(lit)?;
-                        return Ok(Self::Single(single));
+                        return Ok(Self::Single(single, span));
                     }
                     _ => {}
                 }
@@ -96,11 +351,23 @@ impl Parse for FieldLevelMacroArguments {
         let punct: Punctuated<FieldArgument, Token![,]> =
             content.parse_terminated(FieldArgument::parse)?;
         let mut builder = FieldLevelMacroArgumentsBuilder::default();
+        // Descending ranges are allowed only among pieces of split fields
+        let split = punct
+            .iter()
+            .filter(|item| matches!(item, FieldArgument::Range(_) | FieldArgument::Single(..)))
+            .count()
+            > 1;
 
         for item in punct {
             match item {
                 FieldArgument::Offset(offset) => {
                     let existing = builder.offset.replace(offset);
+                    if !builder.pieces.is_empty() {
+                        return Err(Error::new(
+                            input.span(),
+                            "cannot use `offset` with `range` or `single` field definition",
+                        ));
+                    }
                     if existing.is_some() {
                         return Err(Error::new(
                             input.span(),
@@ -110,6 +377,12 @@ impl Parse for FieldLevelMacroArguments {
                 }
                 FieldArgument::Size(size) => {
                     let existing = builder.size.replace(size);
+                    if !builder.pieces.is_empty() {
+                        return Err(Error::new(
+                            input.span(),
+                            "cannot use `size` with `range` or `single` field definition",
+                        ));
+                    }
                     if existing.is_some() {
                         return Err(Error::new(
                             input.span(),
@@ -118,24 +391,94 @@ impl Parse for FieldLevelMacroArguments {
                     }
                 }
                 FieldArgument::Range(range) => {
-                    let ex1 = builder.size.replace(range.len);
-                    let ex2 = builder.offset.replace(range.lo);
-                    if ex1.is_some() || ex2.is_some() {
+                    if builder.size.is_some() || builder.offset.is_some() {
                         return Err(Error::new(
                             input.span(),
                             "cannot use other bit specifiers with `range` field definition",
                         ));
                     }
+                    builder.add_piece(common::range_from_pat(&range, split)?, range.span())?;
+                }
+                FieldArgument::Signed(signed) => {
+                    let existing = builder.signed.replace(signed.map(|box signed| signed));
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `signed` entries in field attribute",
+                        ));
+                    }
+                }
+                FieldArgument::ValueRange(range) => {
+                    let existing = builder.range.replace(range);
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `range` entries in field attribute",
+                        ));
+                    }
+                }
+                FieldArgument::Shift(shift) => {
+                    let existing = builder.shift.replace(shift);
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `shift` entries in field attribute",
+                        ));
+                    }
+                }
+                FieldArgument::Encoding(encoding) => {
+                    let existing = builder.encoding.replace(encoding);
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `encoding` entries in field attribute",
+                        ));
+                    }
+                }
+                FieldArgument::Fixed(fixed) => {
+                    let existing = builder.fixed.replace(fixed);
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `fixed` entries in field attribute",
+                        ));
+                    }
+                }
+                FieldArgument::Order(order) => {
+                    let existing = builder.order.replace(order);
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `order` entries in field attribute",
+                        ));
+                    }
+                }
+                FieldArgument::Count(count) => {
+                    let existing = builder.count.replace(count);
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `count` entries in field attribute",
+                        ));
+                    }
+                }
+                FieldArgument::Stride(stride) => {
+                    let existing = builder.stride.replace(stride);
+                    if existing.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `stride` entries in field attribute",
+                        ));
+                    }
                 }
-                FieldArgument::Single(single) => {
-                    let ex1 = builder.size.replace(1);
-                    let ex2 = builder.offset.replace(single);
-                    if ex1.is_some() || ex2.is_some() {
+                FieldArgument::Single(single, span) => {
+                    if builder.size.is_some() || builder.offset.is_some() {
                         return Err(Error::new(
                             input.span(),
                             "cannot use other bit specifiers with `single` field definition",
                         ));
                     }
+                    builder.add_piece(Position { lo: single, len: 1 }, span)?;
                 }
             }
         }
@@ -9,11 +9,14 @@ use syn::{parse_macro_input, parse_quote, ItemEnum, LitInt, Type};
 
 use crate::bit_access::BitAccess;
 
+mod arch_register;
 mod bit_access;
 mod bit_field;
+mod bus;
 mod common;
 mod extra_enum_access;
 mod field_level_macro_arguments;
+mod mmio;
 mod top_level_macro_arguments;
 
 #[proc_macro_attribute]
@@ -0,0 +1,162 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_quote, Expr, Type};
+
+use crate::top_level_macro_arguments::{Endian, Via};
+
+/// Backend for memory mapped registers, accessed with volatile reads and writes at `address`.
+/// Indexed registers are laid out one after another, starting at `address`.
+pub struct Mmio {
+    pub address: Expr,
+    /// Type of a single byte lane, when field writes may use transfers narrower than `base_type`.
+    pub lane: Option<Type>,
+    /// Register forbids full width transfers, all accesses are split into lanes.
+    pub lanes_only: bool,
+}
+
+impl Mmio {
+    pub fn read_via(&self, base_type: &Type, indexed: bool) -> Via {
+        let ptr = self.ptr(base_type, quote! { *const }, indexed);
+
+        Via::Statements(match self.only_lane() {
+            None => parse_quote! {
+                unsafe {
+                    value = core::ptr::read_volatile(#ptr);
+                }
+            },
+            Some(lane) => parse_quote! {
+                unsafe {
+                    let ptr = #ptr as *const #lane;
+                    let mut bytes = [0u8; core::mem::size_of::<#base_type>()];
+                    for (lane, chunk) in bytes.chunks_exact_mut(core::mem::size_of::<#lane>()).enumerate() {
+                        chunk.copy_from_slice(&core::ptr::read_volatile(ptr.add(lane)).to_ne_bytes());
+                    }
+                    value = <#base_type>::from_ne_bytes(bytes);
+                }
+            },
+        })
+    }
+
+    pub fn write_via(&self, base_type: &Type, indexed: bool) -> Via {
+        let ptr = self.ptr(base_type, quote! { *mut }, indexed);
+
+        Via::Statements(match self.only_lane() {
+            None => parse_quote! {
+                unsafe {
+                    core::ptr::write_volatile(#ptr, value);
+                }
+            },
+            Some(lane) => {
+                let lane_at = Self::lane_at(lane);
+                parse_quote! {
+                    unsafe {
+                        let ptr = #ptr as *mut #lane;
+                        let bytes = value.to_ne_bytes();
+                        #lane_at
+                        for lane in 0..core::mem::size_of::<#base_type>() / core::mem::size_of::<#lane>() {
+                            core::ptr::write_volatile(ptr.add(lane), lane_at(&bytes, lane));
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Body of `write_raw` storing a field which fits in a single lane with one narrow transfer.
+    /// Lanes covered by `mask` completely are written without reading them. Other writes are
+    /// full width read-modify-writes, unless the register is `lanes_only` - then only lanes
+    /// overlapping `mask` are updated, each with a read-modify-write of a single lane.
+    pub fn masked_writer(
+        &self,
+        base_type: &Type,
+        endian: Option<Endian>,
+        indexed: bool,
+    ) -> Option<TokenStream2> {
+        let lane = self.lane.as_ref()?;
+        let ptr = self.ptr(base_type, quote! { *mut }, indexed);
+        let lane_at = Self::lane_at(lane);
+        let (value, mask) = match endian {
+            Some(endian) => (
+                endian.encode(quote! { new_value }),
+                endian.encode(quote! { mask }),
+            ),
+            None => (quote! { new_value }, quote! { mask }),
+        };
+
+        if !self.lanes_only {
+            return Some(quote! {
+                unsafe {
+                    let lanes = #ptr as *mut #lane;
+                    let value = #value;
+                    let mask = #mask;
+                    let value_bytes = value.to_ne_bytes();
+                    let mask_bytes = mask.to_ne_bytes();
+                    #lane_at
+                    let mut overlapping = (0..core::mem::size_of::<#base_type>() / core::mem::size_of::<#lane>())
+                        .filter(|lane| lane_at(&mask_bytes, *lane) != 0);
+                    match (overlapping.next(), overlapping.next()) {
+                        (Some(lane), None) => {
+                            let lane_mask = lane_at(&mask_bytes, lane);
+                            let mut lane_value = lane_at(&value_bytes, lane);
+                            if lane_mask != <#lane>::MAX {
+                                let current = core::ptr::read_volatile(#ptr).to_ne_bytes();
+                                lane_value |= lane_at(&current, lane) & !lane_mask;
+                            }
+                            core::ptr::write_volatile(lanes.add(lane), lane_value);
+                        }
+                        _ => {
+                            let current = core::ptr::read_volatile(#ptr);
+                            core::ptr::write_volatile(#ptr, (current & !mask) | value);
+                        }
+                    }
+                }
+            });
+        }
+
+        Some(quote! {
+            unsafe {
+                let ptr = #ptr as *mut #lane;
+                let value = #value.to_ne_bytes();
+                let mask = #mask.to_ne_bytes();
+                #lane_at
+                for lane in 0..core::mem::size_of::<#base_type>() / core::mem::size_of::<#lane>() {
+                    let lane_mask = lane_at(&mask, lane);
+                    if lane_mask == 0 {
+                        continue;
+                    }
+                    let mut lane_value = lane_at(&value, lane);
+                    if lane_mask != <#lane>::MAX {
+                        lane_value |= core::ptr::read_volatile(ptr.add(lane)) & !lane_mask;
+                    }
+                    core::ptr::write_volatile(ptr.add(lane), lane_value);
+                }
+            }
+        })
+    }
+
+    /// Lane of registers which can't be accessed with full width at all.
+    fn only_lane(&self) -> Option<&Type> {
+        self.lane.as_ref().filter(|_| self.lanes_only)
+    }
+
+    /// Closure extracting `lane`-th lane from byte representation of a value.
+    fn lane_at(lane: &Type) -> TokenStream2 {
+        quote! {
+            let lane_at = |bytes: &[u8], lane: usize| {
+                let size = core::mem::size_of::<#lane>();
+                let mut lane_bytes = [0u8; core::mem::size_of::<#lane>()];
+                lane_bytes.copy_from_slice(&bytes[lane * size..(lane + 1) * size]);
+                <#lane>::from_ne_bytes(lane_bytes)
+            };
+        }
+    }
+
+    fn ptr(&self, base_type: &Type, kind: TokenStream2, indexed: bool) -> TokenStream2 {
+        let address = &self.address;
+        if indexed {
+            quote! { ((#address) as #kind #base_type).add(index as usize) }
+        } else {
+            quote! { ((#address) as #kind #base_type) }
+        }
+    }
+}
@@ -1,32 +1,80 @@
-use proc_macro2::Ident;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
 use syn::{
+    parenthesized,
     parse::{Parse, ParseStream},
+    parse_quote,
     punctuated::Punctuated,
+    token,
+    Block,
     Error,
     Expr,
+    ExprBlock,
+    LitBool,
+    LitInt,
     LitStr,
+    Path,
     Token,
     Type,
 };
 
+use crate::{
+    arch_register::ArchRegister,
+    bus::{Bus, BusKind},
+    common,
+    mmio::Mmio,
+};
+
 mod kw {
     syn::custom_keyword!(base_type);
     syn::custom_keyword!(kind);
     syn::custom_keyword!(write_via);
     syn::custom_keyword!(read_via);
+    syn::custom_keyword!(sysreg);
+    syn::custom_keyword!(csr);
+    syn::custom_keyword!(msr);
+    syn::custom_keyword!(index);
+    syn::custom_keyword!(count);
+    syn::custom_keyword!(stride);
+    syn::custom_keyword!(storage);
+    syn::custom_keyword!(rmw);
+    syn::custom_keyword!(critical_section);
+    syn::custom_keyword!(custom);
+    syn::custom_keyword!(ordering);
+    syn::custom_keyword!(endian);
+    syn::custom_keyword!(address);
+    syn::custom_keyword!(access_width);
+    syn::custom_keyword!(lanes_only);
+    syn::custom_keyword!(bus);
+    syn::custom_keyword!(device);
+    syn::custom_keyword!(register);
+    syn::custom_keyword!(register_width);
+    syn::custom_keyword!(error);
+    syn::custom_keyword!(volatile);
+    syn::custom_keyword!(verify_writes);
+    syn::custom_keyword!(iter_set);
+}
+
+/// Code used by global registers to access the underlying value.
+pub enum Via {
+    /// Statements operating on `value` directly, given either as a string or a `{ ... }` block.
+    Statements(Expr),
+    /// Function path or closure. Readers are called with no arguments and return the value,
+    /// writers are called with the value.
+    Call(Expr),
 }
 
 pub struct GlobalReadOnly {
-    pub read_via: Expr,
+    pub read_via: Via,
 }
 
 pub struct GlobalReadWrite {
-    pub read_via: Expr,
-    pub write_via: Expr,
+    pub read_via: Via,
+    pub write_via: Via,
 }
 
 pub struct GlobalWriteOnly {
-    pub write_via: Expr,
+    pub write_via: Via,
 }
 
 pub enum Implementation {
@@ -39,14 +87,86 @@ pub enum Implementation {
 pub struct TopLevelMacroArguments {
     pub base_type: Type,
     pub implementation: Implementation,
+    /// `write_raw` body updating only masked bits, provided by some architecture registers.
+    pub masked_write_via: Option<TokenStream2>,
+    pub index: Option<IndexArg>,
+    pub storage: Storage,
+    pub rmw: Option<Rmw>,
+    pub ordering: AccessOrdering,
+    pub endian: Option<Endian>,
+    pub bus: Option<Bus>,
+    /// Error type of fallible backends.
+    pub error: Option<Type>,
+    /// Read back every write and panic when the register doesn't hold the written value.
+    pub verify_writes: bool,
+    /// Generate `{Register}Flag` enum of single-bit fields and `iter_set`.
+    pub iter_set: bool,
+}
+
+/// Byte order of global registers, values are swapped when it differs from the CPU byte order.
+#[derive(Clone, Copy)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// Ordering of global register accesses relative to other memory accesses.
+#[derive(Clone, Copy)]
+pub enum AccessOrdering {
+    /// No ordering promises, accesses may be reordered with surrounding memory operations.
+    Relaxed,
+    /// Prevents only the compiler from reordering memory operations around register accesses.
+    Compiler,
+    /// CPU memory fence before every write and after every read. Not an I/O barrier.
+    Fenced,
+}
+
+/// Guard around read-modify-write sequences of global registers.
+pub enum Rmw {
+    /// `critical_section::with`, re-exported by bitaccess behind `critical-section` feature.
+    CriticalSection,
+    /// User provided `fn<R>(f: impl FnOnce() -> R) -> R`.
+    Custom(Path),
+}
+
+/// How inline registers keep their value.
+pub enum Storage {
+    Plain,
+    /// Value kept in an atomic integer, the contained type is the atomic counterpart of `base_type`.
+    Atomic(Box<Type>),
+}
+
+/// Index of a register within a family of identical registers, eg. `DBGBVR<n>_EL1`.
+pub struct IndexArg {
+    pub ty: Type,
+    pub count: LitInt,
 }
 
 #[derive(Default)]
 pub struct TopLevelMacroArgumentsBuilder {
     base_type: Option<Type>,
     kind: Option<KindArg>,
-    write_via: Option<Expr>,
-    read_via: Option<Expr>,
+    write_via: Option<Via>,
+    read_via: Option<Via>,
+    arch_register: Option<ArchRegister>,
+    index: Option<Type>,
+    count: Option<LitInt>,
+    stride: Option<LitInt>,
+    atomic: Option<bool>,
+    rmw: Option<Rmw>,
+    ordering: Option<AccessOrdering>,
+    endian: Option<Endian>,
+    address: Option<Mmio>,
+    access_width: Option<LitInt>,
+    lanes_only: Option<LitBool>,
+    bus: Option<(BusKind, bool)>,
+    device: Option<LitInt>,
+    register: Option<LitInt>,
+    register_width: Option<LitInt>,
+    error: Option<Type>,
+    volatile: Option<LitBool>,
+    verify_writes: Option<LitBool>,
+    iter_set: Option<LitBool>,
 }
 
 pub struct KindArg {
@@ -57,8 +177,27 @@ pub struct KindArg {
 pub enum TopLevelArgument {
     BaseType(Type),
     Kind(KindArg),
-    WriteVia(Expr),
-    ReadVia(Expr),
+    WriteVia(Via),
+    ReadVia(Via),
+    ArchRegister(ArchRegister),
+    Index(Type),
+    Count(LitInt),
+    Stride(LitInt),
+    Atomic(bool),
+    Rmw(Rmw),
+    Ordering(AccessOrdering),
+    Endian(Endian),
+    Address(Box<Mmio>),
+    AccessWidth(LitInt),
+    LanesOnly(LitBool),
+    Bus(BusKind, bool),
+    Device(LitInt),
+    Register(LitInt),
+    RegisterWidth(LitInt),
+    Error(Type),
+    Volatile(LitBool),
+    VerifyWrites(LitBool),
+    IterSet(LitBool),
 }
 
 impl Parse for TopLevelArgument {
@@ -91,25 +230,289 @@ impl Parse for TopLevelArgument {
         } else if lookahead.peek(kw::read_via) {
             let _: kw::read_via = input.parse()?;
             let _: Token![=] = input.parse()?;
-            let ins: LitStr = input.parse()?;
-
-            let expr = syn::parse_str(&ins.value())?;
 
-            Ok(Self::ReadVia(expr))
+            Ok(Self::ReadVia(input.parse()?))
         } else if lookahead.peek(kw::write_via) {
             let _: kw::write_via = input.parse()?;
             let _: Token![=] = input.parse()?;
-            let ins: LitStr = input.parse()?;
 
-            let expr = syn::parse_str(&ins.value())?;
+            Ok(Self::WriteVia(input.parse()?))
+        } else if lookahead.peek(kw::sysreg) {
+            let _: kw::sysreg = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            Ok(Self::ArchRegister(ArchRegister::SysReg(input.parse()?)))
+        } else if lookahead.peek(kw::csr) {
+            let _: kw::csr = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let csr: LitInt = input.parse()?;
+            csr.base10_parse::<u16>()?;
+
+            Ok(Self::ArchRegister(ArchRegister::Csr(csr)))
+        } else if lookahead.peek(kw::msr) {
+            let _: kw::msr = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let msr: LitInt = input.parse()?;
+            msr.base10_parse::<u32>()?;
+
+            Ok(Self::ArchRegister(ArchRegister::Msr(msr)))
+        } else if lookahead.peek(kw::index) {
+            let _: kw::index = input.parse()?;
+            let _: Token![:] = input.parse()?;
+
+            Ok(Self::Index(input.parse()?))
+        } else if lookahead.peek(kw::count) {
+            let _: kw::count = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let count: LitInt = input.parse()?;
+            count.base10_parse::<u64>()?;
+
+            Ok(Self::Count(count))
+        } else if lookahead.peek(kw::stride) {
+            let _: kw::stride = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let stride: LitInt = input.parse()?;
+            if stride.base10_parse::<u32>()? == 0 {
+                return Err(Error::new(stride.span(), "`stride` has to be positive"));
+            }
+
+            Ok(Self::Stride(stride))
+        } else if lookahead.peek(kw::storage) {
+            let _: kw::storage = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let storage: Ident = input.parse()?;
 
-            Ok(Self::WriteVia(expr))
+            Ok(match storage.to_string().as_str() {
+                "atomic" => Self::Atomic(true),
+                "plain" => Self::Atomic(false),
+                _ => return Err(Error::new(storage.span(), "unsupported storage")),
+            })
+        } else if lookahead.peek(kw::rmw) {
+            let _: kw::rmw = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            let lookahead = input.lookahead1();
+            if lookahead.peek(kw::critical_section) {
+                let _: kw::critical_section = input.parse()?;
+
+                Ok(Self::Rmw(Rmw::CriticalSection))
+            } else if lookahead.peek(kw::custom) {
+                let _: kw::custom = input.parse()?;
+                let content;
+                let _ = parenthesized!(content in input);
+
+                Ok(Self::Rmw(Rmw::Custom(content.parse()?)))
+            } else {
+                Err(lookahead.error())
+            }
+        } else if lookahead.peek(kw::ordering) {
+            let _: kw::ordering = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let ordering: Ident = input.parse()?;
+
+            Ok(match ordering.to_string().as_str() {
+                "relaxed" => Self::Ordering(AccessOrdering::Relaxed),
+                "compiler" => Self::Ordering(AccessOrdering::Compiler),
+                "fenced" => Self::Ordering(AccessOrdering::Fenced),
+                _ => return Err(Error::new(ordering.span(), "unsupported ordering")),
+            })
+        } else if lookahead.peek(kw::endian) {
+            let _: kw::endian = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let endian: Ident = input.parse()?;
+
+            Ok(match endian.to_string().as_str() {
+                "big" => Self::Endian(Endian::Big),
+                "little" => Self::Endian(Endian::Little),
+                _ => return Err(Error::new(endian.span(), "unsupported endian")),
+            })
+        } else if lookahead.peek(kw::address) {
+            let _: kw::address = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            Ok(Self::Address(Box::new(Mmio {
+                address: input.parse()?,
+                lane: None,
+                lanes_only: false,
+            })))
+        } else if lookahead.peek(kw::access_width) {
+            let _: kw::access_width = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let access_width: LitInt = input.parse()?;
+
+            match access_width.base10_parse::<u32>()? {
+                8 | 16 | 32 | 64 => Ok(Self::AccessWidth(access_width)),
+                _ => Err(Error::new(
+                    access_width.span(),
+                    "unsupported access width, expected 8, 16, 32 or 64",
+                )),
+            }
+        } else if lookahead.peek(kw::lanes_only) {
+            let _: kw::lanes_only = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            Ok(Self::LanesOnly(input.parse()?))
+        } else if lookahead.peek(kw::bus) {
+            let _: kw::bus = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let bus: Ident = input.parse()?;
+
+            Ok(match bus.to_string().as_str() {
+                "i2c" => Self::Bus(BusKind::I2c, false),
+                "spi" => Self::Bus(BusKind::Spi, false),
+                "async_i2c" => Self::Bus(BusKind::I2c, true),
+                "async_spi" => Self::Bus(BusKind::Spi, true),
+                _ => return Err(Error::new(bus.span(), "unsupported bus")),
+            })
+        } else if lookahead.peek(kw::device) {
+            let _: kw::device = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let device: LitInt = input.parse()?;
+            device.base10_parse::<u8>()?;
+
+            Ok(Self::Device(device))
+        } else if lookahead.peek(kw::register_width) {
+            let _: kw::register_width = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let register_width: LitInt = input.parse()?;
+            match register_width.base10_parse::<u32>()? {
+                8 | 16 => Ok(Self::RegisterWidth(register_width)),
+                _ => Err(Error::new(
+                    register_width.span(),
+                    "unsupported register width, expected 8 or 16",
+                )),
+            }
+        } else if lookahead.peek(kw::register) {
+            let _: kw::register = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let register: LitInt = input.parse()?;
+            register.base10_parse::<u16>()?;
+
+            Ok(Self::Register(register))
+        } else if lookahead.peek(kw::error) {
+            let _: kw::error = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            Ok(Self::Error(input.parse()?))
+        } else if lookahead.peek(kw::volatile) {
+            let _: kw::volatile = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            Ok(Self::Volatile(input.parse()?))
+        } else if lookahead.peek(kw::verify_writes) {
+            let _: kw::verify_writes = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            Ok(Self::VerifyWrites(input.parse()?))
+        } else if lookahead.peek(kw::iter_set) {
+            let _: kw::iter_set = input.parse()?;
+            let _: Token![=] = input.parse()?;
+
+            Ok(Self::IterSet(input.parse()?))
         } else {
             Err(Error::new(input.span(), "unsupported top level argument"))
         }
     }
 }
 
+impl Parse for Via {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            let ins: LitStr = input.parse()?;
+
+            Ok(Self::Statements(syn::parse_str(&ins.value())?))
+        } else if input.peek(token::Brace) {
+            let block: Block = input.parse()?;
+
+            Ok(Self::Statements(Expr::Block(ExprBlock {
+                attrs: Vec::new(),
+                label: None,
+                block,
+            })))
+        } else {
+            Ok(Self::Call(input.parse()?))
+        }
+    }
+}
+
+impl Via {
+    /// Tokens assigning the register contents to `value`.
+    /// Indexed registers pass `index` as the first argument to calls.
+    /// Calls of fallible backends return `Result`, which is propagated with `?`.
+    pub fn reader(&self, indexed: bool, fallible: bool) -> TokenStream2 {
+        let question = fallible.then(|| quote! { ? });
+        match self {
+            Self::Statements(expr) => quote! { #expr; },
+            Self::Call(expr) if indexed => quote! {
+                let read_via = #expr;
+                value = read_via(index)#question;
+            },
+            Self::Call(expr) => quote! {
+                let read_via = #expr;
+                value = read_via()#question;
+            },
+        }
+    }
+
+    /// Tokens storing `value` in the register.
+    /// Indexed registers pass `index` as the first argument to calls.
+    /// Calls of fallible backends return `Result`, which is propagated with `?`.
+    pub fn writer(&self, indexed: bool, fallible: bool) -> TokenStream2 {
+        let question = fallible.then(|| quote! { ? });
+        match self {
+            Self::Statements(expr) => quote! { #expr; },
+            Self::Call(expr) if indexed => quote! {
+                let write_via = #expr;
+                write_via(index, value)#question;
+            },
+            Self::Call(expr) => quote! {
+                let write_via = #expr;
+                write_via(value)#question;
+            },
+        }
+    }
+}
+
+impl AccessOrdering {
+    /// Fence emitted before writing and after reading the register.
+    pub fn fence(&self) -> Option<TokenStream2> {
+        match self {
+            Self::Relaxed => None,
+            Self::Compiler => Some(quote! {
+                core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+            }),
+            Self::Fenced => Some(quote! {
+                core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+            }),
+        }
+    }
+}
+
+impl Endian {
+    /// Tokens converting `value` read from the register to CPU byte order.
+    pub fn after_read(&self, base_type: &Type) -> TokenStream2 {
+        match self {
+            Self::Big => quote! { value = <#base_type>::from_be(value); },
+            Self::Little => quote! { value = <#base_type>::from_le(value); },
+        }
+    }
+
+    /// Tokens converting `value` to register byte order before it's written.
+    pub fn before_write(&self) -> TokenStream2 {
+        let value = self.encode(quote! { value });
+
+        quote! { let value = #value; }
+    }
+
+    /// Expression converting `value` from CPU to register byte order.
+    pub fn encode(&self, value: TokenStream2) -> TokenStream2 {
+        match self {
+            Self::Big => quote! { #value.to_be() },
+            Self::Little => quote! { #value.to_le() },
+        }
+    }
+}
+
 impl Parse for TopLevelMacroArguments {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let punct: Punctuated<TopLevelArgument, Token![,]> =
@@ -155,15 +558,186 @@ impl Parse for TopLevelMacroArguments {
                         ));
                     }
                 }
+                TopLevelArgument::ArchRegister(arch_register) => {
+                    let ex = builder.arch_register.replace(arch_register);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `sysreg`, `csr` or `msr` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Index(index) => {
+                    let ex = builder.index.replace(index);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `index` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Count(count) => {
+                    let ex = builder.count.replace(count);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `count` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Stride(stride) => {
+                    let ex = builder.stride.replace(stride);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `stride` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Rmw(rmw) => {
+                    let ex = builder.rmw.replace(rmw);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `rmw` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Ordering(ordering) => {
+                    let ex = builder.ordering.replace(ordering);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `ordering` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Endian(endian) => {
+                    let ex = builder.endian.replace(endian);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `endian` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Address(box address) => {
+                    let ex = builder.address.replace(address);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `address` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::AccessWidth(access_width) => {
+                    let ex = builder.access_width.replace(access_width);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `access_width` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Bus(kind, asynchronous) => {
+                    let ex = builder.bus.replace((kind, asynchronous));
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `bus` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Device(device) => {
+                    let ex = builder.device.replace(device);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `device` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Register(register) => {
+                    let ex = builder.register.replace(register);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `register` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::RegisterWidth(register_width) => {
+                    let ex = builder.register_width.replace(register_width);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `register_width` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Error(error) => {
+                    let ex = builder.error.replace(error);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `error` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::LanesOnly(lanes_only) => {
+                    let ex = builder.lanes_only.replace(lanes_only);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `lanes_only` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Volatile(volatile) => {
+                    let ex = builder.volatile.replace(volatile);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `volatile` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::VerifyWrites(verify_writes) => {
+                    let ex = builder.verify_writes.replace(verify_writes);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `verify_writes` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::IterSet(iter_set) => {
+                    let ex = builder.iter_set.replace(iter_set);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `iter_set` entries in top level attribute",
+                        ));
+                    }
+                }
+                TopLevelArgument::Atomic(atomic) => {
+                    let ex = builder.atomic.replace(atomic);
+                    if ex.is_some() {
+                        return Err(Error::new(
+                            input.span(),
+                            "multiple `storage` entries in top level attribute",
+                        ));
+                    }
+                }
             }
         }
 
-        Ok(builder.build())
+        builder.build()
     }
 }
 
 impl TopLevelMacroArgumentsBuilder {
-    fn build(self) -> TopLevelMacroArguments {
+    fn build(self) -> syn::Result<TopLevelMacroArguments> {
         let base_type = if let Some(base_type) = self.base_type {
             base_type
         } else {
@@ -175,7 +749,223 @@ impl TopLevelMacroArgumentsBuilder {
             write: true,
         });
 
-        let implementation = match (self.read_via, self.write_via, kind) {
+        let index = match (self.index, self.count) {
+            (Some(ty), Some(count)) => Some(IndexArg { ty, count }),
+            (None, None) => None,
+            _ => proc_macro_error::abort_call_site!("`index` and `count` have to be used together"),
+        };
+        let count = index
+            .as_ref()
+            .map(|index| index.count.base10_parse::<u64>())
+            .transpose()?;
+        let stride = match &self.stride {
+            Some(stride) => {
+                if !matches!(
+                    self.arch_register,
+                    Some(ArchRegister::Csr(_) | ArchRegister::Msr(_))
+                ) || index.is_none()
+                {
+                    return Err(Error::new(
+                        stride.span(),
+                        "`stride` is supported only by indexed `csr` and `msr` registers",
+                    ));
+                }
+                stride.base10_parse::<u32>()?
+            }
+            None => 1,
+        };
+
+        let bus = match (self.bus, self.register) {
+            (Some((kind, asynchronous)), Some(register)) => {
+                match (&kind, &self.device) {
+                    (BusKind::I2c, None) => {
+                        proc_macro_error::abort_call_site!("I2C `bus` requires `device`")
+                    }
+                    (BusKind::Spi, Some(device)) => {
+                        return Err(Error::new(
+                            device.span(),
+                            "`device` is not supported by SPI `bus`",
+                        ))
+                    }
+                    _ => {}
+                }
+                if self.read_via.is_some()
+                    || self.write_via.is_some()
+                    || self.arch_register.is_some()
+                    || self.address.is_some()
+                {
+                    proc_macro_error::abort_call_site!(
+                        "`bus` cannot be used with `read_via`, `write_via`, `sysreg`, `csr`, `msr` or `address`"
+                    )
+                }
+                if index.is_some() {
+                    proc_macro_error::abort_call_site!("`index` cannot be used with `bus`")
+                }
+                if asynchronous && self.rmw.is_some() {
+                    proc_macro_error::abort_call_site!(
+                        "`rmw` cannot be used with asynchronous `bus`"
+                    )
+                }
+                let wide = match &self.register_width {
+                    Some(register_width) => register_width.base10_parse::<u32>()? == 16,
+                    None => false,
+                };
+                if !wide && register.base10_parse::<u8>().is_err() {
+                    return Err(Error::new(
+                        register.span(),
+                        "register address doesn't fit in 8 bits, declare `register_width = 16`",
+                    ));
+                }
+                let volatile = self.volatile.as_ref().is_some_and(LitBool::value);
+                if asynchronous && volatile {
+                    proc_macro_error::abort_call_site!(
+                        "`volatile` is supported only by synchronous `bus`"
+                    )
+                }
+                Some(Bus {
+                    kind,
+                    device: self.device,
+                    register,
+                    wide,
+                    asynchronous,
+                    volatile,
+                })
+            }
+            (None, None)
+                if self.device.is_none()
+                    && self.volatile.is_none()
+                    && self.register_width.is_none() =>
+            {
+                None
+            }
+            (None, None) if self.volatile.is_some() => {
+                proc_macro_error::abort_call_site!(
+                    "`volatile` is supported only by `bus` registers"
+                )
+            }
+            (None, None) if self.register_width.is_some() => {
+                proc_macro_error::abort_call_site!(
+                    "`register_width` is supported only by `bus` registers"
+                )
+            }
+            _ => proc_macro_error::abort_call_site!(
+                "`bus`, `device` and `register` have to be used together"
+            ),
+        };
+
+        if self.error.is_some() && self.read_via.is_none() && self.write_via.is_none() {
+            proc_macro_error::abort_call_site!(
+                "`error` is supported only by registers with `read_via` or `write_via`"
+            )
+        }
+        // Bus registers propagate errors of the bus
+        let error = self.error.or_else(|| {
+            bus.as_ref().map(|bus| {
+                let bound = bus.bound();
+                parse_quote! { <BUS as #bound>::Error }
+            })
+        });
+
+        let mut masked_write_via = None;
+        let (read_via, write_via) = match (self.arch_register, self.address) {
+            (Some(_), Some(_)) => proc_macro_error::abort_call_site!(
+                "`address` cannot be used with `sysreg`, `csr` or `msr`"
+            ),
+            (Some(arch_register), None) => {
+                if self.access_width.is_some() || self.lanes_only.is_some() {
+                    proc_macro_error::abort_call_site!(
+                        "`access_width` is supported only by `address` registers"
+                    )
+                }
+                if self.read_via.is_some() || self.write_via.is_some() {
+                    proc_macro_error::abort_call_site!(
+                        "`read_via` and `write_via` cannot be used with `sysreg`, `csr` or `msr`"
+                    )
+                }
+                if self.endian.is_some() {
+                    proc_macro_error::abort_call_site!(
+                        "`endian` cannot be used with `sysreg`, `csr` or `msr`"
+                    )
+                }
+                let read_via = kind
+                    .read
+                    .then(|| arch_register.read_via(&base_type, count, stride))
+                    .transpose()?;
+                let write_via = kind
+                    .write
+                    .then(|| arch_register.write_via(count, stride))
+                    .transpose()?;
+                if kind.read && kind.write {
+                    masked_write_via = arch_register.masked_writer(count, stride)?;
+                }
+                (read_via, write_via)
+            }
+            (None, Some(mut address)) => {
+                if self.read_via.is_some() || self.write_via.is_some() {
+                    proc_macro_error::abort_call_site!(
+                        "`read_via` and `write_via` cannot be used with `address`"
+                    )
+                }
+                if let Some(access_width) = &self.access_width {
+                    let base_width = match common::bit_width(&base_type) {
+                        Some(base_width) => base_width,
+                        None => {
+                            return Err(Error::new_spanned(
+                                &base_type,
+                                "`access_width` requires primitive integer `base_type`",
+                            ))
+                        }
+                    };
+                    let width = access_width.base10_parse::<u32>()?;
+                    if width > base_width {
+                        return Err(Error::new(
+                            access_width.span(),
+                            "`access_width` cannot be wider than `base_type`",
+                        ));
+                    }
+                    if width < base_width {
+                        let lane = Ident::new(&format!("u{}", width), access_width.span());
+                        address.lane = Some(parse_quote! { #lane });
+                    }
+                }
+                if let Some(lanes_only) = &self.lanes_only {
+                    if self.access_width.is_none() {
+                        return Err(Error::new(
+                            lanes_only.span(),
+                            "`lanes_only` requires `access_width`",
+                        ));
+                    }
+                    address.lanes_only = lanes_only.value();
+                }
+                let read_via = kind
+                    .read
+                    .then(|| address.read_via(&base_type, count.is_some()));
+                let write_via = kind
+                    .write
+                    .then(|| address.write_via(&base_type, count.is_some()));
+                if kind.read && kind.write {
+                    masked_write_via =
+                        address.masked_writer(&base_type, self.endian, count.is_some());
+                }
+                (read_via, write_via)
+            }
+            (None, None) => {
+                if self.access_width.is_some() || self.lanes_only.is_some() {
+                    proc_macro_error::abort_call_site!(
+                        "`access_width` is supported only by `address` registers"
+                    )
+                }
+                match &bus {
+                    Some(bus) => (
+                        kind.read.then(|| bus.read_via(&base_type)),
+                        kind.write.then(|| bus.write_via()),
+                    ),
+                    None => (self.read_via, self.write_via),
+                }
+            }
+        };
+
+        let implementation = match (read_via, write_via, kind) {
             (None, None, kind) => Implementation::Inline(kind),
             (
                 Some(read_via),
@@ -209,14 +999,82 @@ impl TopLevelMacroArgumentsBuilder {
             ),
         };
 
-        TopLevelMacroArguments {
+        if index.is_some() && matches!(implementation, Implementation::Inline(_)) {
+            proc_macro_error::abort_call_site!("`index` is supported only by global registers")
+        }
+
+        let storage = match self.atomic {
+            Some(true) => {
+                if !matches!(implementation, Implementation::Inline(_)) {
+                    proc_macro_error::abort_call_site!(
+                        "`storage = atomic` is supported only by inline registers"
+                    )
+                }
+                Storage::Atomic(Box::new(common::atomic_type(&base_type)?))
+            }
+            Some(false) | None => Storage::Plain,
+        };
+
+        if self.rmw.is_some() && !matches!(implementation, Implementation::GlobalReadWrite(_)) {
+            proc_macro_error::abort_call_site!(
+                "`rmw` is supported only by readable and writable global registers"
+            )
+        }
+
+        if self.ordering.is_some() && matches!(implementation, Implementation::Inline(_)) {
+            proc_macro_error::abort_call_site!("`ordering` is supported only by global registers")
+        }
+
+        if self.endian.is_some() && matches!(implementation, Implementation::Inline(_)) {
+            proc_macro_error::abort_call_site!("`endian` is supported only by global registers")
+        }
+
+        let verify_writes = self.verify_writes.as_ref().is_some_and(LitBool::value);
+        if verify_writes && !matches!(implementation, Implementation::GlobalReadWrite(_)) {
+            proc_macro_error::abort_call_site!(
+                "`verify_writes` is supported only by readable and writable global registers"
+            )
+        }
+        // `try_` accessors report backend errors only, so they couldn't report a mismatch
+        if verify_writes && error.is_some() {
+            proc_macro_error::abort_call_site!(
+                "`verify_writes` cannot be used with `error` or `bus`, use `write_verified` instead"
+            )
+        }
+
+        Ok(TopLevelMacroArguments {
             base_type,
             implementation,
-        }
+            masked_write_via,
+            index,
+            storage,
+            rmw: self.rmw,
+            ordering: self.ordering.unwrap_or(AccessOrdering::Relaxed),
+            // Most of the external chips send the most significant byte first
+            endian: self.endian.or_else(|| bus.as_ref().map(|_| Endian::Big)),
+            bus,
+            error,
+            verify_writes,
+            iter_set: self.iter_set.as_ref().is_some_and(LitBool::value),
+        })
     }
 }
 
 impl TopLevelMacroArguments {
+    pub fn is_async(&self) -> bool {
+        matches!(
+            self.bus,
+            Some(Bus {
+                asynchronous: true,
+                ..
+            })
+        )
+    }
+
+    pub fn is_atomic(&self) -> bool {
+        matches!(self.storage, Storage::Atomic(_))
+    }
+
     pub fn is_read(&self) -> bool {
         match self.implementation {
             Implementation::Inline(KindArg { read, .. }) => read,